@@ -12,6 +12,8 @@ macro_rules! eval_benchmark {
       use rainbow_core::standalone::Value;
       let ns = Namespace::new_with_prelude().unwrap().into_shared();
       let script = Script::compile(ns, $rainbow_src).unwrap();
+      // only benchmarks with inputs insert any
+      #[allow(unused_mut)]
       let mut inputs: HashMap<String, Value> = HashMap::with_capacity(16);
       $(
         inputs.insert(
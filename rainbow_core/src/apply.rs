@@ -8,7 +8,7 @@ pub struct Apply<V: Debug + PartialEq + Clone, K = u16> {
 
 impl<V: Debug + PartialEq + Clone, K> From<Vec<(K, V)>> for Apply<V, K> {
     fn from(args: Vec<(K, V)>) -> Self {
-        Apply { args: args }
+        Apply { args }
     }
 }
 
@@ -28,12 +28,12 @@ impl<V: Debug + PartialEq + Clone + WithError, K: 'static> Apply<V, K> {
         &self.args[0].0
     }
 
-    pub fn get<U: ?Sized>(&self, name: &U) -> Option<&V>
+    pub fn get<U>(&self, name: &U) -> Option<&V>
     where
         K: ::std::borrow::Borrow<U>,
-        U: PartialEq<K>,
+        U: ?Sized + PartialEq<K>,
     {
-        for &(ref xname, ref x) in self.args.iter() {
+        for (xname, x) in self.args.iter() {
             if name == xname {
                 return Some(x);
             }
@@ -41,10 +41,10 @@ impl<V: Debug + PartialEq + Clone + WithError, K: 'static> Apply<V, K> {
         None
     }
 
-    pub fn demand<U: ?Sized>(&self, name: &U) -> Result<&V, V::Error>
+    pub fn demand<U>(&self, name: &U) -> Result<&V, V::Error>
     where
         K: ::std::borrow::Borrow<U>,
-        U: PartialEq<K> + Debug,
+        U: ?Sized + PartialEq<K> + Debug,
     {
         self.get(name)
             .ok_or_else(|| V::Error::from(format!("Missing required argument {:?}", name)))
@@ -52,7 +52,7 @@ impl<V: Debug + PartialEq + Clone + WithError, K: 'static> Apply<V, K> {
 
     pub fn all<U: PartialEq<K>>(&self, name: U) -> Vec<&V> {
         let mut result: Vec<&V> = Vec::with_capacity(self.args.len());
-        for &(ref xname, ref x) in self.args.iter() {
+        for (xname, x) in self.args.iter() {
             if name == *xname {
                 result.push(x)
             }
@@ -60,15 +60,30 @@ impl<V: Debug + PartialEq + Clone + WithError, K: 'static> Apply<V, K> {
         result
     }
 
-    pub fn iter(&self) -> ::std::slice::Iter<(K, V)> {
+    pub fn iter(&self) -> ::std::slice::Iter<'_, (K, V)> {
         self.args.iter()
     }
 
-    pub fn into_iter(self) -> ::std::vec::IntoIter<(K, V)> {
-        self.args.into_iter()
-    }
-
     pub fn len(&self) -> usize {
         self.args.len()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.args.is_empty()
+    }
+}
+
+impl<V: Debug + PartialEq + Clone + WithError, K: 'static> Default for Apply<V, K> {
+    fn default() -> Self {
+        Apply::new()
+    }
+}
+
+impl<V: Debug + PartialEq + Clone, K> IntoIterator for Apply<V, K> {
+    type Item = (K, V);
+    type IntoIter = ::std::vec::IntoIter<(K, V)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.args.into_iter()
+    }
 }
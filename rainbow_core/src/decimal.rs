@@ -0,0 +1,400 @@
+//! Exact decimal numbers of any size, for amounts of money and scripts that can't have their
+//! numbers rounded by floats.
+
+use std::cmp::Ordering;
+use std::fmt::{Display, Error as FmtError, Formatter};
+use std::str::FromStr;
+
+/// The most digits a parsed number can have before or after its point, so that a literal like
+/// `1e20000000000` is rejected instead of taking all the memory there is.
+const MAX_DIGITS: i64 = 1_000;
+
+/// A decimal number, stored as its digits and the number of them that come after the point.
+///
+/// The scale is kept as written, so `12.50` is displayed with two decimal places, but compares
+/// equal to `12.5`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Decimal {
+    negative: bool,
+    /// Least significant first, without leading zeros, so zero has no digits.
+    digits: Vec<u8>,
+    scale: u32,
+}
+
+impl Decimal {
+    pub fn zero() -> Self {
+        Decimal {
+            negative: false,
+            digits: Vec::new(),
+            scale: 0,
+        }
+    }
+
+    pub fn one() -> Self {
+        Decimal::new(false, vec![1], 0)
+    }
+
+    /// The number of digits after the decimal point.
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    /// The exact value of a float as it would be printed, or `None` for infinities and NaN.
+    pub fn from_f64(n: f64) -> Option<Self> {
+        if n.is_finite() {
+            n.to_string().parse().ok()
+        } else {
+            None
+        }
+    }
+
+    /// The nearest float to this number.
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap_or(0.0)
+    }
+
+    pub fn add(&self, other: &Decimal) -> Decimal {
+        let scale = self.scale.max(other.scale);
+        let (left, right) = (self.rescaled(scale), other.rescaled(scale));
+        if left.negative == right.negative {
+            return Decimal::new(
+                left.negative,
+                add_digits(&left.digits, &right.digits),
+                scale,
+            );
+        }
+        match cmp_digits(&left.digits, &right.digits) {
+            Ordering::Less => Decimal::new(
+                right.negative,
+                sub_digits(&right.digits, &left.digits),
+                scale,
+            ),
+            _ => Decimal::new(
+                left.negative,
+                sub_digits(&left.digits, &right.digits),
+                scale,
+            ),
+        }
+    }
+
+    pub fn sub(&self, other: &Decimal) -> Decimal {
+        self.add(&other.negated())
+    }
+
+    pub fn mul(&self, other: &Decimal) -> Decimal {
+        Decimal::new(
+            self.negative != other.negative,
+            mul_digits(&self.digits, &other.digits),
+            self.scale + other.scale,
+        )
+    }
+
+    /// This divided by `other`, rounded to `scale` decimal places, or `None` when dividing by
+    /// zero.
+    pub fn div(&self, other: &Decimal, scale: u32) -> Option<Decimal> {
+        if other.is_zero() {
+            return None;
+        }
+        // shift the dividend so that integer division leaves one digit more than needed
+        let shift = scale + 1 + other.scale;
+        let mut dividend = vec![0; shift as usize];
+        dividend.extend_from_slice(&self.digits);
+        let quotient = div_digits(&dividend, &other.digits);
+        let exact = Decimal::new(
+            self.negative != other.negative,
+            quotient,
+            scale + 1 + self.scale,
+        );
+        Some(exact.round(scale))
+    }
+
+    /// Round to `scale` decimal places, with halves rounded away from zero.
+    pub fn round(&self, scale: u32) -> Decimal {
+        if scale >= self.scale {
+            return self.rescaled(scale);
+        }
+        let dropped = (self.scale - scale) as usize;
+        if dropped > self.digits.len() {
+            return Decimal::new(false, Vec::new(), scale);
+        }
+        let round_up = dropped > 0 && self.digits[dropped - 1] >= 5;
+        let mut digits = self.digits[dropped..].to_vec();
+        if round_up {
+            digits = add_digits(&digits, &[1]);
+        }
+        Decimal::new(self.negative, digits, scale)
+    }
+
+    /// The same number without trailing zeros after the point.
+    pub fn normalized(&self) -> Decimal {
+        let zeros = self
+            .digits
+            .iter()
+            .take(self.scale as usize)
+            .take_while(|&&d| d == 0)
+            .count();
+        Decimal::new(
+            self.negative,
+            self.digits[zeros..].to_vec(),
+            self.scale - zeros as u32,
+        )
+    }
+
+    pub fn negated(&self) -> Decimal {
+        Decimal::new(!self.negative, self.digits.clone(), self.scale)
+    }
+
+    fn new(negative: bool, mut digits: Vec<u8>, scale: u32) -> Decimal {
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        Decimal {
+            negative: negative && !digits.is_empty(),
+            digits,
+            scale,
+        }
+    }
+
+    /// The same number with at least `scale` decimal places.
+    fn rescaled(&self, scale: u32) -> Decimal {
+        if scale <= self.scale {
+            return self.clone();
+        }
+        let mut digits = vec![0; (scale - self.scale) as usize];
+        digits.extend_from_slice(&self.digits);
+        Decimal::new(self.negative, digits, scale)
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = String;
+
+    /// Parse a number like `-12.50` or `1.5e3`.
+    fn from_str(text: &str) -> Result<Decimal, String> {
+        let invalid = || format!("`{}` is not a valid number", text);
+        let (mantissa, exponent) = match text.find(['e', 'E']) {
+            Some(i) => (
+                &text[..i],
+                text[i + 1..].parse::<i64>().map_err(|_| invalid())?,
+            ),
+            None => (text, 0),
+        };
+        let (negative, mantissa) = match mantissa.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, mantissa),
+        };
+        let (whole, fraction) = match mantissa.find('.') {
+            Some(i) if i + 1 < mantissa.len() => (&mantissa[..i], &mantissa[i + 1..]),
+            Some(_) => return Err(invalid()),
+            None => (mantissa, ""),
+        };
+        let all_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+        if whole.is_empty() || !all_digits(whole) || !all_digits(fraction) {
+            return Err(invalid());
+        }
+        let digits: Vec<u8> = whole
+            .bytes()
+            .chain(fraction.bytes())
+            .rev()
+            .map(|b| b - b'0')
+            .collect();
+        let scale = (fraction.len() as i64).saturating_sub(exponent);
+        if scale > MAX_DIGITS || (whole.len() as i64).saturating_sub(scale) > MAX_DIGITS {
+            return Err(invalid());
+        }
+        if scale >= 0 {
+            Ok(Decimal::new(negative, digits, scale as u32))
+        } else {
+            let mut shifted = vec![0; (-scale) as usize];
+            shifted.extend(digits);
+            Ok(Decimal::new(negative, shifted, 0))
+        }
+    }
+}
+
+impl Display for Decimal {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
+        let scale = self.scale as usize;
+        let mut text = String::with_capacity(self.digits.len() + 3);
+        if self.negative {
+            text.push('-');
+        }
+        let width = self.digits.len().max(scale + 1);
+        for i in (0..width).rev() {
+            text.push((b'0' + self.digits.get(i).cloned().unwrap_or(0)) as char);
+            if i == scale && scale > 0 {
+                text.push('.');
+            }
+        }
+        f.write_str(&text)
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Decimal) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Decimal) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Decimal) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (negative, _) => {
+                let scale = self.scale.max(other.scale);
+                let ordering =
+                    cmp_digits(&self.rescaled(scale).digits, &other.rescaled(scale).digits);
+                if negative {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            }
+        }
+    }
+}
+
+fn cmp_digits(left: &[u8], right: &[u8]) -> Ordering {
+    let trimmed = |digits: &[u8]| digits.iter().rposition(|&d| d != 0).map_or(0, |i| i + 1);
+    let (left, right) = (&left[..trimmed(left)], &right[..trimmed(right)]);
+    left.len()
+        .cmp(&right.len())
+        .then_with(|| left.iter().rev().cmp(right.iter().rev()))
+}
+
+fn add_digits(left: &[u8], right: &[u8]) -> Vec<u8> {
+    let mut sum = Vec::with_capacity(left.len().max(right.len()) + 1);
+    let mut carry = 0;
+    for i in 0..left.len().max(right.len()) {
+        let digit = left.get(i).cloned().unwrap_or(0) + right.get(i).cloned().unwrap_or(0) + carry;
+        sum.push(digit % 10);
+        carry = digit / 10;
+    }
+    if carry > 0 {
+        sum.push(carry);
+    }
+    sum
+}
+
+/// `left - right`, where `left` must not be smaller.
+fn sub_digits(left: &[u8], right: &[u8]) -> Vec<u8> {
+    let mut difference = Vec::with_capacity(left.len());
+    let mut borrow = 0;
+    for (i, &digit) in left.iter().enumerate() {
+        let subtrahend = right.get(i).cloned().unwrap_or(0) + borrow;
+        if digit >= subtrahend {
+            difference.push(digit - subtrahend);
+            borrow = 0;
+        } else {
+            difference.push(digit + 10 - subtrahend);
+            borrow = 1;
+        }
+    }
+    difference
+}
+
+fn mul_digits(left: &[u8], right: &[u8]) -> Vec<u8> {
+    let mut product = vec![0u32; left.len() + right.len()];
+    for (i, &l) in left.iter().enumerate() {
+        for (j, &r) in right.iter().enumerate() {
+            product[i + j] += l as u32 * r as u32;
+        }
+    }
+    let mut carry = 0;
+    for digit in product.iter_mut() {
+        *digit += carry;
+        carry = *digit / 10;
+        *digit %= 10;
+    }
+    product.into_iter().map(|d| d as u8).collect()
+}
+
+/// Integer division of `left` by `right`, dropping the remainder.
+fn div_digits(left: &[u8], right: &[u8]) -> Vec<u8> {
+    let mut quotient = vec![0; left.len()];
+    let mut remainder: Vec<u8> = Vec::new();
+    for i in (0..left.len()).rev() {
+        remainder.insert(0, left[i]);
+        let mut count = 0;
+        while cmp_digits(&remainder, right) != Ordering::Less {
+            remainder = sub_digits(&remainder, right);
+            count += 1;
+        }
+        quotient[i] = count;
+    }
+    quotient
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(text: &str) -> Decimal {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        for text in [
+            "0",
+            "12.50",
+            "-0.05",
+            "100",
+            "123456789012345678901234567890.1",
+        ]
+        .iter()
+        {
+            assert_eq!(dec(text).to_string(), *text);
+        }
+        assert_eq!(dec("1.5e3").to_string(), "1500");
+        assert_eq!(dec("15e-3").to_string(), "0.015");
+        assert_eq!(dec("-0.00").to_string(), "0.00");
+        assert!("1.".parse::<Decimal>().is_err());
+        assert!("abc".parse::<Decimal>().is_err());
+        assert_eq!(dec("1e999").to_string().len(), 1000);
+        assert!("1e20000000000".parse::<Decimal>().is_err());
+        assert!("1e-4294967297".parse::<Decimal>().is_err());
+        assert!("1e-9223372036854775808".parse::<Decimal>().is_err());
+    }
+
+    #[test]
+    fn test_arithmetic_is_exact() {
+        assert_eq!(dec("0.1").add(&dec("0.2")), dec("0.3"));
+        assert_eq!(dec("12.50").add(&dec("0.5")).to_string(), "13.00");
+        assert_eq!(dec("1").sub(&dec("2.25")).to_string(), "-1.25");
+        assert_eq!(dec("-1.5").mul(&dec("-1.5")).to_string(), "2.25");
+        assert_eq!(
+            dec("99999999999999999999").mul(&dec("99999999999999999999")),
+            dec("9999999999999999999800000000000000000001")
+        );
+        assert!(dec("1.0") == dec("1"));
+        assert!(dec("-2") < dec("-1.5"));
+    }
+
+    #[test]
+    fn test_division_and_rounding() {
+        assert_eq!(dec("10").div(&dec("3"), 2).unwrap().to_string(), "3.33");
+        assert_eq!(dec("2").div(&dec("3"), 2).unwrap().to_string(), "0.67");
+        assert_eq!(dec("-1").div(&dec("8"), 2).unwrap().to_string(), "-0.13");
+        assert_eq!(dec("1").div(&dec("0.25"), 0).unwrap().to_string(), "4");
+        assert_eq!(dec("1").div(&dec("0"), 2), None);
+        assert_eq!(dec("2.345").round(2).to_string(), "2.35");
+        assert_eq!(dec("0.004").round(2).to_string(), "0.00");
+        assert_eq!(dec("3.1400").normalized().to_string(), "3.14");
+        assert_eq!(Decimal::from_f64(0.1).unwrap(), dec("0.1"));
+        assert_eq!(dec("2.5").to_f64(), 2.5);
+    }
+}
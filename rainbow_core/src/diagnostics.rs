@@ -0,0 +1,213 @@
+//! Rendering of errors alongside the source code that caused them.
+//!
+//! Rainbow authors are mostly not programmers, so every diagnostic shows the offending line with
+//! the problem underlined and a plain-English explanation, rather than a dump of compiler state.
+
+use crate::typing::TypeError;
+
+/// How diagnostics should be decorated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    /// Plain text, suitable for logs and storing in databases.
+    Plain,
+    /// Colored with ANSI escape codes, for terminals.
+    Ansi,
+}
+
+impl Style {
+    fn paint(self, code: &str, text: &str) -> String {
+        match self {
+            Style::Plain => text.to_string(),
+            Style::Ansi => format!("\x1b[{}m{}\x1b[0m", code, text),
+        }
+    }
+}
+
+const BOLD: &str = "1";
+const BOLD_RED: &str = "1;31";
+const BOLD_BLUE: &str = "1;34";
+
+/// Render one type error against the source it was found in.
+pub fn render_type_error(src: &str, err: &TypeError, style: Style) -> String {
+    let location = err.location();
+    render_snippet(
+        src,
+        location.start_pos,
+        location.end_pos,
+        &format!("{}", err),
+        style,
+    )
+}
+
+/// Render every error in `errs`, separated by blank lines.
+pub fn render_type_errors(src: &str, errs: &[TypeError], style: Style) -> String {
+    errs.iter()
+        .map(|err| render_type_error(src, err, style))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Render `message` with the line of `src` containing `start_pos` and a caret underline spanning
+/// `start_pos..end_pos` (clipped to the end of that line).
+pub fn render_snippet(
+    src: &str,
+    start_pos: usize,
+    end_pos: usize,
+    message: &str,
+    style: Style,
+) -> String {
+    let start_pos = start_pos.min(src.len());
+    let end_pos = end_pos.max(start_pos).min(src.len());
+
+    let line_start = src[..start_pos].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let line_end = src[start_pos..]
+        .find('\n')
+        .map(|i| start_pos + i)
+        .unwrap_or_else(|| src.len());
+    let line = src[line_start..line_end].trim_end_matches('\r');
+    let line_number = src[..line_start].matches('\n').count() + 1;
+    let column = src[line_start..start_pos].chars().count() + 1;
+
+    // keep tabs in the padding so the carets line up with the source line
+    let padding: String = src[line_start..start_pos]
+        .chars()
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    // spans can end with the whitespace after them, which isn't worth underlining
+    let underline_len = src[start_pos..end_pos.min(line_end)]
+        .trim_end()
+        .chars()
+        .count()
+        .max(1);
+    let underline = "^".repeat(underline_len);
+
+    let number = line_number.to_string();
+    let blank_gutter = " ".repeat(number.len());
+
+    let mut out = String::new();
+    out.push_str(&style.paint(BOLD_RED, "error"));
+    out.push_str(&style.paint(BOLD, &format!(": {}", message)));
+    out.push('\n');
+    out.push_str(&format!(
+        "{}{} line {}, column {}\n",
+        blank_gutter,
+        style.paint(BOLD_BLUE, "-->"),
+        line_number,
+        column
+    ));
+    out.push_str(&format!(
+        "{} {}\n",
+        blank_gutter,
+        style.paint(BOLD_BLUE, "|")
+    ));
+    out.push_str(&format!(
+        "{} {}\n",
+        style.paint(BOLD_BLUE, &format!("{} |", number)),
+        line
+    ));
+    out.push_str(&format!(
+        "{} {} {}{}\n",
+        blank_gutter,
+        style.paint(BOLD_BLUE, "|"),
+        padding,
+        style.paint(BOLD_RED, &underline)
+    ));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::*;
+    use crate::typing;
+    use crate::typing::Type;
+
+    fn errors_for(src: &str, globals: Vec<(String, Type)>) -> Vec<TypeError> {
+        let ns = init_namespace();
+        typing::type_of(&ns, globals, &parse(&ns, src)).errors
+    }
+
+    #[test]
+    fn test_render_incompatible_argument() {
+        let src = "calc: 1\n  plus: name";
+        let errors = errors_for(src, vec![("name".into(), Type::Str)]);
+        assert_eq!(
+            render_type_errors(src, &errors, Style::Plain),
+            "error: expected a number, but found a string
+ --> line 2, column 9
+  |
+2 |   plus: name
+  |         ^^^^
+"
+        );
+    }
+
+    #[test]
+    fn test_render_follows_type_path() {
+        let mut ns = init_namespace();
+        ns.define(|f| {
+            let item = Type::record_from_iter(vec![("cost", Type::Num)]);
+            f.required_arg("total", Type::list_of(item));
+            f.returns(Type::Num);
+            f.callback(|_args, _vm| Err(String::from("unimplemented")));
+        })
+        .unwrap();
+        let item = Type::record_from_iter(vec![("cost", Type::Str)]);
+        let globals = vec![("items".to_string(), Type::list_of(item))];
+        let errors = typing::type_of(&ns, globals, &parse(&ns, "total: items")).errors;
+        let messages: Vec<String> = errors.iter().map(|err| format!("{}", err)).collect();
+        assert_eq!(
+            messages,
+            vec!["expected a number, but found a string (in field `cost` of list element)"]
+        );
+    }
+
+    #[test]
+    fn test_render_ansi() {
+        let src = "upperCase: 1";
+        let errors = errors_for(src, vec![]);
+        let rendered = render_type_errors(src, &errors, Style::Ansi);
+        assert!(rendered.starts_with("\x1b[1;31merror\x1b[0m"));
+        assert!(rendered.contains("\x1b[1;31m^\x1b[0m"));
+    }
+
+    #[test]
+    fn test_render_unguarded_call() {
+        let src = "crash: \"oh no\"";
+        let errors = errors_for(src, vec![]);
+        assert_eq!(
+            render_type_errors(src, &errors, Style::Plain),
+            "error: `crash:` can fail, so it must be inside the first part of `try: ... or: ...`
+ --> line 1, column 1
+  |
+1 | crash: \"oh no\"
+  | ^^^^^^
+"
+        );
+    }
+
+    #[test]
+    fn test_render_suggestion() {
+        let src = "countfrom: 1 to: 3";
+        let errors = errors_for(src, vec![]);
+        assert_eq!(
+            render_type_errors(src, &errors, Style::Plain),
+            "error: there is no function called `countfrom:`, did you mean `countFrom:`?
+ --> line 1, column 1
+  |
+1 | countfrom: 1 to: 3
+  | ^^^^^^^^^^
+"
+        );
+    }
+
+    #[test]
+    fn test_render_block_arity() {
+        let errors = errors_for("each: [1 2] do: { a b => a }", vec![]);
+        let messages: Vec<String> = errors.iter().map(|err| format!("{}", err)).collect();
+        assert_eq!(
+            messages,
+            vec!["this block takes 2 inputs but only 1 is given"]
+        );
+    }
+}
@@ -0,0 +1,456 @@
+//! The canonical source formatter.
+//!
+//! The tree is first turned into a document of text and possible line breaks, Wadler-style. Each
+//! list, record, block and keyword call is a group that is printed on one line if it fits within
+//! the target width, or broken across lines with its contents indented otherwise. Comments always
+//! end their line, so a group containing one is always broken.
+//!
+//! Formatting works on the tree as written, before implicit blocks are added or removed, so it
+//! doesn't need a namespace and never changes what a script means.
+
+use id_tree::NodeId;
+
+use crate::arena::Arena;
+use crate::frontend::parser;
+use crate::frontend::{NodeType, ParseError, SyntaxTree};
+use crate::namespace::NumberMode;
+
+/// The line width used by `rainbow fmt` unless another is given.
+pub const DEFAULT_WIDTH: usize = 80;
+
+const INDENT: usize = 2;
+
+/// Format `input` in the canonical style, keeping lines within `width` where possible.
+///
+/// Fails with the first syntax error if `input` doesn't parse. Formatting already formatted
+/// source returns it unchanged.
+pub fn format_source(input: &str, width: usize) -> Result<String, ParseError<'_>> {
+    let (mut tree, mut errors) =
+        parser::parse_tree(&Arena::with_capacity(0), NumberMode::Float, input)?;
+    if !errors.is_empty() {
+        return Err(errors.remove(0));
+    }
+    parser::attach_comments(&mut tree)?;
+
+    let root_id = tree.nodes.root_node_id().unwrap().clone();
+    let mut doc = node_doc(&tree, &root_id);
+    if let Some(trivia) = tree.trivia.get(&root_id) {
+        for comment in &trivia.following {
+            doc.push(Doc::HardLine);
+            doc.push(Doc::Text(comment.clone()));
+        }
+    }
+
+    let mut printer = Printer {
+        width,
+        out: String::with_capacity(input.len()),
+        column: 0,
+        indent: 0,
+        at_line_start: true,
+    };
+    printer.print(&doc, false);
+    printer.line_break();
+    Ok(printer.out)
+}
+
+#[derive(Debug, Clone)]
+enum Doc {
+    Text(String),
+    /// A space, or a line break when its group is broken.
+    Line,
+    /// Nothing, or a line break when its group is broken.
+    SoftLine,
+    /// Always a line break.
+    HardLine,
+    /// Contents indented one level further when they are broken across lines.
+    Nest(Vec<Doc>),
+    /// Contents printed on one line if they fit, otherwise with their lines broken.
+    Group(Vec<Doc>),
+}
+
+fn text<S: Into<String>>(s: S) -> Doc {
+    Doc::Text(s.into())
+}
+
+/// The document for a node, including its comments.
+fn node_doc(tree: &SyntaxTree, node_id: &NodeId) -> Vec<Doc> {
+    let mut doc = Vec::new();
+    let trivia = tree.trivia.get(node_id);
+    for comment in trivia.iter().flat_map(|trivia| &trivia.leading) {
+        doc.push(text(comment.as_str()));
+        doc.push(Doc::HardLine);
+    }
+
+    let node = tree.nodes.get(node_id).unwrap();
+    let children = node.children();
+    match node.data().node_type {
+        NodeType::Primitive(_) => {
+            // only money literals have spaces in them, between the amount and currency
+            let words: Vec<&str> = tree
+                .node_id_str(node_id)
+                .unwrap()
+                .split_whitespace()
+                .collect();
+            doc.push(text(words.join(" ")));
+        }
+        NodeType::Ident(_) | NodeType::Keyword(_) | NodeType::Concat => {
+            doc.push(text(tree.node_id_str(node_id).unwrap()));
+        }
+        NodeType::Variable => {
+            for (i, child_id) in children.iter().enumerate() {
+                let separator = if i == 0 { "" } else { "." };
+                doc.extend(separated(tree, separator, child_id));
+            }
+        }
+        NodeType::List | NodeType::Record if children.is_empty() => {
+            let empty = if node.data().node_type == NodeType::List {
+                "[]"
+            } else {
+                "[=]"
+            };
+            doc.push(text(empty));
+        }
+        NodeType::List | NodeType::Record => {
+            let mut items = Vec::new();
+            for (i, child_id) in children.iter().enumerate() {
+                items.push(if i == 0 { Doc::SoftLine } else { Doc::Line });
+                items.extend(node_doc(tree, child_id));
+            }
+            doc.push(Doc::Group(vec![
+                text("["),
+                Doc::Nest(items),
+                Doc::SoftLine,
+                text("]"),
+            ]));
+        }
+        NodeType::Update => {
+            let mut items = vec![Doc::SoftLine];
+            items.extend(node_doc(tree, &children[0]));
+            items.push(Doc::Line);
+            items.push(text("with"));
+            for child_id in &children[1..] {
+                items.push(Doc::Line);
+                items.extend(node_doc(tree, child_id));
+            }
+            doc.push(Doc::Group(vec![
+                text("["),
+                Doc::Nest(items),
+                Doc::SoftLine,
+                text("]"),
+            ]));
+        }
+        NodeType::RecordEntry => {
+            doc.extend(node_doc(tree, &children[0]));
+            doc.extend(separated(tree, " = ", &children[1]));
+        }
+        NodeType::Apply => {
+            let mut rest = Vec::new();
+            for child_id in &children[1..] {
+                rest.push(Doc::Line);
+                rest.extend(node_doc(tree, child_id));
+            }
+            let mut group = node_doc(tree, &children[0]);
+            group.push(Doc::Nest(rest));
+            doc.push(Doc::Group(group));
+        }
+        NodeType::Argument => {
+            doc.extend(node_doc(tree, &children[0]));
+            doc.extend(separated(tree, " ", &children[1]));
+        }
+        NodeType::Block => {
+            let mut group = vec![text("{")];
+            if children.len() > 1 {
+                let mut args = separated(tree, " ", &children[0]);
+                // a comment after the last argument goes after the arrow, which ends the line
+                let comment = trailing_comment(&mut args);
+                group.extend(args);
+                group.push(text(" =>"));
+                group.extend(comment);
+            }
+            let mut body = vec![Doc::Line];
+            body.extend(node_doc(tree, children.last().unwrap()));
+            group.push(Doc::Nest(body));
+            group.push(Doc::Line);
+            group.push(text("}"));
+            doc.push(Doc::Group(group));
+        }
+        NodeType::BlockArgs => {
+            for (i, child_id) in children.iter().enumerate() {
+                // an argument after a comment starts its own line
+                let separator = match doc.last() {
+                    _ if i == 0 => "",
+                    Some(Doc::HardLine) => "",
+                    _ => " ",
+                };
+                doc.extend(separated(tree, separator, child_id));
+            }
+        }
+        NodeType::Root => {
+            for (i, child_id) in children.iter().enumerate() {
+                if i > 0 {
+                    doc.push(Doc::HardLine);
+                }
+                doc.extend(node_doc(tree, child_id));
+            }
+        }
+        NodeType::Binding => {
+            doc.extend(node_doc(tree, &children[0]));
+            doc.extend(separated(tree, " = ", &children[1]));
+        }
+        // formatting refuses scripts with syntax errors, so there are no holes
+        NodeType::Hole => {}
+    }
+
+    if let Some(comment) = trivia.and_then(|trivia| trivia.trailing.as_ref()) {
+        doc.push(text(format!(" {}", comment)));
+        doc.push(Doc::HardLine);
+    }
+    doc
+}
+
+/// A node following `separator` on the same line, or starting an indented line if it has comments
+/// that must come before it.
+/// Take the trailing comment, and the line break after it, off the end of `doc`.
+fn trailing_comment(doc: &mut Vec<Doc>) -> Vec<Doc> {
+    if let Some(Doc::Nest(nested)) = doc.last_mut() {
+        return trailing_comment(nested);
+    }
+    match doc.as_slice() {
+        [.., Doc::Text(comment), Doc::HardLine] if comment.starts_with(" #") => {
+            doc.split_off(doc.len() - 2)
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn separated(tree: &SyntaxTree, separator: &str, node_id: &NodeId) -> Vec<Doc> {
+    let has_leading = tree
+        .trivia
+        .get(node_id)
+        .is_some_and(|trivia| !trivia.leading.is_empty());
+    if has_leading {
+        let mut nested = vec![Doc::HardLine];
+        nested.extend(node_doc(tree, node_id));
+        vec![text(separator.trim_end()), Doc::Nest(nested)]
+    } else {
+        let mut doc = vec![text(separator)];
+        doc.extend(node_doc(tree, node_id));
+        doc
+    }
+}
+
+/// The width of `docs` printed on one line, or `None` if they can't be.
+fn flat_width(docs: &[Doc]) -> Option<usize> {
+    let mut width = 0;
+    for doc in docs {
+        width += match *doc {
+            Doc::Text(ref s) => s.chars().count(),
+            Doc::Line => 1,
+            Doc::SoftLine => 0,
+            Doc::HardLine => return None,
+            Doc::Nest(ref inner) | Doc::Group(ref inner) => flat_width(inner)?,
+        };
+    }
+    Some(width)
+}
+
+struct Printer {
+    width: usize,
+    out: String,
+    column: usize,
+    indent: usize,
+    /// Set after a line break, the indentation is only written along with the next text so that
+    /// lines never have trailing whitespace and consecutive breaks never leave blank lines.
+    at_line_start: bool,
+}
+
+impl Printer {
+    fn print(&mut self, docs: &[Doc], flat: bool) {
+        for doc in docs {
+            match *doc {
+                Doc::Text(ref s) => self.text(s),
+                Doc::Line if flat => self.text(" "),
+                Doc::SoftLine if flat => {}
+                Doc::Line | Doc::SoftLine | Doc::HardLine => self.line_break(),
+                Doc::Nest(ref inner) => {
+                    self.indent += INDENT;
+                    self.print(inner, flat);
+                    self.indent -= INDENT;
+                }
+                Doc::Group(ref inner) => {
+                    let fits =
+                        flat || flat_width(inner).is_some_and(|w| self.column + w <= self.width);
+                    self.print(inner, fits);
+                }
+            }
+        }
+    }
+
+    fn text(&mut self, s: &str) {
+        if self.at_line_start {
+            for _ in 0..self.indent {
+                self.out.push(' ');
+            }
+            self.column = self.indent;
+            self.at_line_start = false;
+        }
+        self.out.push_str(s);
+        self.column += s.chars().count();
+    }
+
+    fn line_break(&mut self) {
+        if !self.at_line_start {
+            self.out.push('\n');
+            self.column = 0;
+            self.at_line_start = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_formats(src: &str, width: usize, expected: &str) {
+        let formatted = format_source(src, width).unwrap();
+        assert_eq!(formatted, expected);
+        assert_eq!(format_source(&formatted, width).unwrap(), formatted);
+    }
+
+    #[test]
+    fn test_short_scripts_stay_on_one_line() {
+        assert_formats("calc:   1\n   plus: 2", 80, "calc: 1 plus: 2\n");
+        assert_formats("[a=1   b=[ ]  c = [=]]", 80, "[a = 1 b = [] c = [=]]\n");
+        assert_formats(
+            "each: xs do: {x=>x.name}",
+            80,
+            "each: xs do: { x => x.name }\n",
+        );
+    }
+
+    #[test]
+    fn test_long_scripts_break_within_width() {
+        let src = "each: offices do: { office => [
+            name = try: office.name else: { stringify: office.id }
+            employees = each: office.employees do: { e => upperCase: e.name }
+        ] }";
+        assert_formats(
+            src,
+            60,
+            "each: offices
+  do: { office =>
+    [
+      name = try: office.name else: { stringify: office.id }
+      employees = each: office.employees
+        do: { e => upperCase: e.name }
+    ]
+  }
+",
+        );
+        assert_formats(
+            src,
+            40,
+            "each: offices
+  do: { office =>
+    [
+      name = try: office.name
+        else: { stringify: office.id }
+      employees = each: office.employees
+        do: { e => upperCase: e.name }
+    ]
+  }
+",
+        );
+    }
+
+    #[test]
+    fn test_keeps_comments() {
+        let src = "# totals per order
+each: orders # every order
+  do: { order => calc: order.price
+    # before tax
+    times: order.quantity }
+# the end";
+        assert_formats(
+            src,
+            80,
+            "# totals per order
+each: orders # every order
+  do: { order =>
+    calc: order.price
+      # before tax
+      times: order.quantity
+  }
+# the end
+",
+        );
+
+        assert_formats(
+            "[ # first\n 1 2 # two\n ]",
+            80,
+            "[\n  # first\n  1\n  2 # two\n]\n",
+        );
+    }
+
+    #[test]
+    fn test_keeps_literals_as_written() {
+        assert_formats(
+            "[1.50 100_000 \"a\\\"b\"]",
+            80,
+            "[1.50 100_000 \"a\\\"b\"]\n",
+        );
+        assert_formats("\"hi {  x.name }!\"", 80, "\"hi {  x.name }!\"\n");
+        assert_formats("[12.50\n   USD 2024-03-15]", 80, "[12.50 USD 2024-03-15]\n");
+    }
+
+    #[test]
+    fn test_puts_bindings_on_their_own_lines() {
+        assert_formats(
+            "# totals\nsubtotal=calc: a times: b total = calc: subtotal\n plus: 1 # tax\n[subtotal total]",
+            80,
+            "# totals\nsubtotal = calc: a times: b\ntotal = calc: subtotal plus: 1 # tax\n[subtotal total]\n",
+        );
+    }
+
+    #[test]
+    fn test_breaks_long_updates_before_with() {
+        assert_formats(
+            "[order with status=\"paid\"]",
+            80,
+            "[order with status = \"paid\"]\n",
+        );
+        assert_formats(
+            "[order with status=\"paid\" paidAt=now]",
+            20,
+            "[\n  order\n  with\n  status = \"paid\"\n  paidAt = now\n]\n",
+        );
+    }
+
+    #[test]
+    fn test_rejects_syntax_errors() {
+        assert!(format_source("calc: 1 plus:", 80).is_err());
+    }
+
+    #[test]
+    fn test_comments_anywhere_are_kept_and_stable() {
+        let sources = [
+            "x # a\n . y # b",
+            "{ # args\n a # first\n b => # body\n a }",
+            "{ x => # c\n x }",
+            "[ n # name\n = # value\n 1 ]",
+            "calc: # left\n 1 plus: 2 # right\n# end\n# really",
+            "[ # nothing\n ]",
+            "[ order # it\n with # changes\n status = 1 ]",
+        ];
+        for src in sources.iter() {
+            let formatted = format_source(src, 80).unwrap();
+            assert_eq!(
+                formatted.matches('#').count(),
+                src.matches('#').count(),
+                "{}",
+                formatted
+            );
+            assert_eq!(format_source(&formatted, 80).unwrap(), formatted);
+        }
+    }
+}
@@ -13,9 +13,7 @@ pub fn rewrite<NS: INamespace>(ns: &NS, tree: &mut SyntaxTree) -> Result<(), Nod
             let children = node.children();
             let arg0 = tree.nodes.get(&children[0]).unwrap();
             let signature = {
-                let func_name = tree
-                    .node_id_str(&arg0.children()[0])?
-                    .trim_right_matches(':');
+                let func_name = tree.node_id_str(&arg0.children()[0])?.trim_end_matches(':');
                 let sig = ns.get_signature(func_name);
                 // can't rewrite args if we don't have a signature
                 if sig.is_none() {
@@ -24,7 +22,7 @@ pub fn rewrite<NS: INamespace>(ns: &NS, tree: &mut SyntaxTree) -> Result<(), Nod
                 sig.unwrap()
             };
             for arg_node_id in children {
-                let arg_children = tree.nodes.get(&arg_node_id)?.children();
+                let arg_children = tree.nodes.get(arg_node_id)?.children();
                 let keyword = tree.node_id_to_symbol_id(&arg_children[0])?;
                 let arg_spec = match signature.arg(keyword) {
                     Some(spec) => spec,
@@ -65,8 +63,8 @@ pub fn rewrite<NS: INamespace>(ns: &NS, tree: &mut SyntaxTree) -> Result<(), Nod
         let block_node_id = tree.nodes.insert(
             Node::new(NodeData {
                 node_type: NodeType::Block,
-                start_pos: start_pos,
-                end_pos: end_pos,
+                start_pos,
+                end_pos,
             }),
             InsertBehavior::UnderNode(&node_id),
         )?;
@@ -1,7 +1,8 @@
-/**
- * This lexer is *not* currently used. I think I wanted to make a hand-written
- * parser for better error messages but I really don't remember why now.
- */
+//! Splits source text into the tokens consumed by the parser.
+//!
+//! The lexer never fails: text it doesn't understand becomes an `Unknown` (or `UnclosedString`)
+//! token so the parser can report it and carry on.
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum TokenKind {
     Colon,
@@ -16,7 +17,13 @@ pub enum TokenKind {
     Bool,
     String,
     Number,
+    /// An ISO-8601 date or date and time, like `2024-03-15T09:30:00Z`.
+    Time,
+    /// An ISO-8601 duration, like `P1DT12H`.
+    Duration,
     UnclosedString,
+    /// A `#` and the rest of the line after it.
+    Comment,
     Unknown,
 }
 
@@ -28,6 +35,12 @@ pub struct Token<'i> {
     pub end_pos: usize,
 }
 
+impl<'i> Token<'i> {
+    pub fn as_str(&self) -> &'i str {
+        &self.input[self.start_pos..self.end_pos]
+    }
+}
+
 pub struct Lexer<'i> {
     input: &'i str,
     current: Option<char>,
@@ -36,42 +49,61 @@ pub struct Lexer<'i> {
 
 pub fn lex<'i>(input: &'i str) -> Lexer<'i> {
     Lexer {
-        input: input,
+        input,
         current: None,
         position: 0,
     }
 }
 
+/// Lex only `input[start..end]`, keeping positions relative to the whole of `input`.
+pub fn lex_range<'i>(input: &'i str, start: usize, end: usize) -> Lexer<'i> {
+    Lexer {
+        input: &input[..end],
+        current: None,
+        position: start,
+    }
+}
+
 impl<'i> Iterator for Lexer<'i> {
     type Item = Token<'i>;
 
     fn next(&mut self) -> Option<Token<'i>> {
         self.seek(|c| !c.is_whitespace());
-        if self.current == None {
-            return None;
-        }
-        Some(match self.current.unwrap() {
-            '"' => match self.distance_to_next(|c| c == '"') {
-                Some(len) => self.token(TokenKind::String, Some(len + 1)),
-                None => self.token(TokenKind::UnclosedString, None),
+        let current = self.current?;
+        Some(match current {
+            '"' => match self.string_len() {
+                Some(len) => self.token(TokenKind::String, Some(len)),
+                // an unclosed string runs to the end of the line
+                None => {
+                    let len = self.distance_to_next(|c| c == '\n');
+                    self.token(TokenKind::UnclosedString, len)
+                }
             },
-            '0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' => {
-                match self.distance_to_next(|c| !c.is_digit(10)) {
-                    Some(len) => match self.input[(self.position + len)..].chars().next() {
-                        Some('.') | Some(',') => {
-                            println!("Found dot at {}", len + self.position);
-                            let start = self.position;
-                            self.position += len;
-                            let full_len = self
-                                .distance_to_next(|c| !c.is_digit(10))
-                                .map(|len2| len2 + len);
-                            self.position = start;
-                            self.token(TokenKind::Number, full_len)
-                        }
-                        _ => self.token(TokenKind::Number, Some(len)),
-                    },
-                    None => self.token(TokenKind::Number, None),
+            '-' => match self.input[self.position..].chars().nth(1) {
+                Some(c) if c.is_ascii_digit() => {
+                    let len = self.number_len();
+                    self.token(TokenKind::Number, Some(len))
                 }
+                _ => {
+                    let len = self.distance_to_next(is_delimiter);
+                    self.token(TokenKind::Unknown, len)
+                }
+            },
+            '0'..='9' if self.at_date() => {
+                let len = self.distance_to_next(|c| !(c.is_alphanumeric() || ":.+-".contains(c)));
+                self.token(TokenKind::Time, len)
+            }
+            'P' if self.duration_len().is_some() => {
+                let len = self.duration_len();
+                self.token(TokenKind::Duration, len)
+            }
+            '0'..='9' => {
+                let len = self.number_len();
+                self.token(TokenKind::Number, Some(len))
+            }
+            '#' => {
+                let len = self.distance_to_next(|c| c == '\n');
+                self.token(TokenKind::Comment, len)
             }
             '{' => self.token(TokenKind::LCurly, Some(1)),
             '}' => self.token(TokenKind::RCurly, Some(1)),
@@ -80,24 +112,24 @@ impl<'i> Iterator for Lexer<'i> {
             ':' => self.token(TokenKind::Colon, Some(1)),
             '.' => self.token(TokenKind::Dot, Some(1)),
             '=' => {
-                if let Some('>') = self.input[self.position..].chars().skip(1).next() {
+                if let Some('>') = self.input[self.position..].chars().nth(1) {
                     self.token(TokenKind::Arrow, Some(2))
                 } else {
                     self.token(TokenKind::Equals, Some(1))
                 }
             }
             ch => {
-                if self.input[self.position..].starts_with("true") {
-                    self.token(TokenKind::Bool, Some(4))
-                } else if self.input[self.position..].starts_with("false") {
-                    self.token(TokenKind::Bool, Some(5))
-                } else if ch.is_alphabetic() {
-                    let len = {
-                        self.distance_to_next(|c| !(c.is_alphanumeric() || c == '-' || c == '_'))
-                    };
-                    self.token(TokenKind::Ident, len)
+                if ch.is_alphabetic() || ch == '_' {
+                    let len = self.distance_to_next(|c| !(c.is_alphanumeric() || c == '_'));
+                    let word = &self.input
+                        [self.position..len.map_or(self.input.len(), |len| self.position + len)];
+                    if word == "true" || word == "false" {
+                        self.token(TokenKind::Bool, len)
+                    } else {
+                        self.token(TokenKind::Ident, len)
+                    }
                 } else {
-                    let len = { self.distance_to_next(|c| c.is_whitespace()) };
+                    let len = self.distance_to_next(is_delimiter);
                     self.token(TokenKind::Unknown, len)
                 }
             }
@@ -105,6 +137,42 @@ impl<'i> Iterator for Lexer<'i> {
     }
 }
 
+/// The length of the string at the start of `text`, including both quotes, or `None` if it is
+/// never closed.
+///
+/// The braces of an interpolation like `"Hello {user.name}"` may contain anything a term can,
+/// including other strings, so a quote only ends the string outside of them.
+fn string_len(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut escaped = false;
+    let mut chars = text.char_indices().skip(1);
+    while let Some((offset, ch)) = chars.next() {
+        match ch {
+            '\n' => return None,
+            _ if escaped => escaped = false,
+            '\\' if depth == 0 => escaped = true,
+            '"' if depth == 0 => return Some(offset + 1),
+            '"' => {
+                // skip to the end of the nested string
+                let len = string_len(&text[offset..])?;
+                while chars
+                    .next()
+                    .is_some_and(|(next, _)| next < offset + len - 1)
+                {}
+            }
+            '{' => depth += 1,
+            '}' if depth > 0 => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Chars that end an unknown token.
+fn is_delimiter(c: char) -> bool {
+    c.is_whitespace() || "[]{}\"#".contains(c)
+}
+
 impl<'i> Lexer<'i> {
     fn seek<F: Fn(char) -> bool>(&mut self, pred: F) {
         if let Some((offset, ch)) = self.search(pred) {
@@ -134,16 +202,114 @@ impl<'i> Lexer<'i> {
         None
     }
 
+    /// The length of the string starting at the current position, including both quotes, or
+    /// `None` if it is never closed.
+    fn string_len(&self) -> Option<usize> {
+        string_len(&self.input[self.position..])
+    }
+
+    /// Does a date like `2024-03-15` start at the current position?
+    fn at_date(&self) -> bool {
+        let rest = &self.input.as_bytes()[self.position..];
+        rest.len() >= 10
+            && rest[..10].iter().enumerate().all(|(i, &b)| {
+                if i == 4 || i == 7 {
+                    b == b'-'
+                } else {
+                    b.is_ascii_digit()
+                }
+            })
+    }
+
+    /// The length of the duration starting at the current position, or `None` if the word there
+    /// isn't one. The whole word has to follow the ISO-8601 grammar, like `P1Y2M3DT4H5M6.5S` or
+    /// `P1W`, so that words like `P1` or `P2x` are still identifiers.
+    fn duration_len(&self) -> Option<usize> {
+        let rest = &self.input.as_bytes()[self.position..];
+        // a number followed by one of the units, returning the position after the unit
+        let component = |i: usize, units: &[u8]| {
+            let mut end = i;
+            while end < rest.len() && rest[end].is_ascii_digit() {
+                end += 1;
+            }
+            if end == i {
+                return None;
+            }
+            if end + 1 < rest.len() && rest[end] == b'.' && rest[end + 1].is_ascii_digit() {
+                end += 1;
+                while end < rest.len() && rest[end].is_ascii_digit() {
+                    end += 1;
+                }
+            }
+            match rest.get(end) {
+                Some(unit) if units.contains(unit) => Some((end + 1, *unit)),
+                _ => None,
+            }
+        };
+        // the components of one part, each unit at most once and in order
+        let part = |mut i: usize, units: &[u8]| {
+            let mut remaining = units;
+            while let Some((end, unit)) = component(i, remaining) {
+                let at = remaining.iter().position(|&u| u == unit).unwrap();
+                remaining = &remaining[at + 1..];
+                i = end;
+            }
+            i
+        };
+        let mut len = part(1, b"YMWD");
+        let date_components = len > 1;
+        if rest.get(len) == Some(&b'T') {
+            let time_start = len + 1;
+            len = part(time_start, b"HMS");
+            if len == time_start {
+                return None;
+            }
+        } else if !date_components {
+            return None;
+        }
+        match rest.get(len) {
+            Some(&b) if b.is_ascii_alphanumeric() || b == b'_' || b >= 0x80 => None,
+            _ => Some(len),
+        }
+    }
+
+    /// The length of the number starting at the current position: an optional minus sign, digits
+    /// that may be separated by underscores, an optional fraction and an optional exponent.
+    fn number_len(&self) -> usize {
+        let rest = &self.input.as_bytes()[self.position..];
+        let digits_from = |mut i: usize, underscores: bool| {
+            while i < rest.len() && (rest[i].is_ascii_digit() || (underscores && rest[i] == b'_')) {
+                i += 1;
+            }
+            i
+        };
+        let mut len = if rest[0] == b'-' { 1 } else { 0 };
+        len = digits_from(len, true);
+        if len + 1 < rest.len() && rest[len] == b'.' && rest[len + 1].is_ascii_digit() {
+            len = digits_from(len + 1, false);
+        }
+        if len < rest.len() && (rest[len] == b'e' || rest[len] == b'E') {
+            let mut exp = len + 1;
+            if exp < rest.len() && (rest[exp] == b'+' || rest[exp] == b'-') {
+                exp += 1;
+            }
+            if exp < rest.len() && rest[exp].is_ascii_digit() {
+                len = digits_from(exp, false);
+            }
+        }
+        len
+    }
+
     #[inline(always)]
     fn token(&mut self, kind: TokenKind, len: Option<usize>) -> Token<'i> {
         let end_pos = len
             .map(|len| len + self.position)
             .unwrap_or(self.input.len());
         let token = Token {
-            kind: kind,
+            kind,
             input: self.input,
             start_pos: self.position,
-            end_pos: end_pos,
+            end_pos,
         };
         self.position = end_pos;
         token
@@ -157,7 +323,7 @@ mod tests {
     macro_rules! assert_lex {
     ($input: expr $(, $variant:ident($start:expr, $end:expr))*) => {{
       let input = $input;
-      let expected = vec![$(Token{ input: input, kind: TokenKind::$variant, start_pos: $start, end_pos: $end }, )*].into_iter();
+      let expected = vec![$(Token{ input, kind: TokenKind::$variant, start_pos: $start, end_pos: $end }, )*].into_iter();
       let actual = lex(input);
       for (e, a) in expected.zip(actual) {
         assert_eq!(e, a);
@@ -225,4 +391,70 @@ mod tests {
             RCurly(38, 39)
         )
     }
+
+    #[test]
+    fn test_words_numbers_and_strings() {
+        assert_lex!(
+            "_x truely true -1_000.5e-3 \"a \\\" b\" - @@ \"open",
+            Ident(0, 2),
+            Ident(3, 9),
+            Bool(10, 14),
+            Number(15, 26),
+            String(27, 35),
+            Unknown(36, 37),
+            Unknown(38, 40),
+            UnclosedString(41, 46)
+        );
+        assert_eq!(lex("a b c").count(), 3);
+    }
+
+    #[test]
+    fn test_interpolated_strings() {
+        assert_lex!(
+            "\"a {upperCase: \"}\"} b\" \"\\{\" \"{x\"",
+            String(0, 22),
+            String(23, 27),
+            UnclosedString(28, 32)
+        );
+    }
+
+    #[test]
+    fn test_times_and_durations() {
+        assert_lex!(
+            "[2024-03-15 2024-03-15T09:30:00.5+01:00] P1DT2H PT1.5S PTotal P 2024",
+            LSquare(0, 1),
+            Time(1, 11),
+            Time(12, 39),
+            RSquare(39, 40),
+            Duration(41, 47),
+            Duration(48, 54),
+            Ident(55, 61),
+            Ident(62, 63),
+            Number(64, 68)
+        );
+        assert_lex!(
+            "P1 P2x PT PT1 P1Y2M3W4DT5H6M7S P1DT P1H P1D]",
+            Ident(0, 2),
+            Ident(3, 6),
+            Ident(7, 9),
+            Ident(10, 13),
+            Duration(14, 30),
+            Ident(31, 35),
+            Ident(36, 39),
+            Duration(40, 43),
+            RSquare(43, 44)
+        );
+    }
+
+    #[test]
+    fn test_comments() {
+        assert_lex!(
+            "# leading\nx # trailing\r\n@#y",
+            Comment(0, 9),
+            Ident(10, 11),
+            Comment(12, 23),
+            Unknown(24, 25),
+            Comment(25, 27)
+        );
+    }
 }
@@ -1,20 +1,19 @@
-use crate::frontend::grammar::Rule;
 use id_tree::NodeIdError;
-use pest::Error as PestError;
 use std::fmt;
 
+use crate::diagnostics::{render_snippet, Style};
+
 #[derive(Debug)]
 pub enum ParseError<'i> {
-    Pest(PestError<'i, Rule>),
+    Syntax {
+        input: &'i str,
+        message: String,
+        start_pos: usize,
+        end_pos: usize,
+    },
     NodeId(NodeIdError),
 }
 
-impl<'i> From<PestError<'i, Rule>> for ParseError<'i> {
-    fn from(error: PestError<'i, Rule>) -> Self {
-        ParseError::Pest(error)
-    }
-}
-
 impl<'i> From<NodeIdError> for ParseError<'i> {
     fn from(error: NodeIdError) -> Self {
         ParseError::NodeId(error)
@@ -24,7 +23,16 @@ impl<'i> From<NodeIdError> for ParseError<'i> {
 impl<'i> fmt::Display for ParseError<'i> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            ParseError::Pest(ref err) => write!(f, "{}", err),
+            ParseError::Syntax {
+                input,
+                ref message,
+                start_pos,
+                end_pos,
+            } => write!(
+                f,
+                "{}",
+                render_snippet(input, start_pos, end_pos, message, Style::Plain)
+            ),
             ParseError::NodeId(ref err) => write!(f, "internal parser error {:?}", err),
         }
     }
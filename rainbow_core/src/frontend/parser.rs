@@ -0,0 +1,1353 @@
+//! A recursive-descent parser that builds a `SyntaxTree` from the tokens of `lexer`.
+//!
+//! The grammar, where `script` is the entry point:
+//!
+//! ```text
+//! script     = binding* term
+//! binding    = ident "=" term
+//! term       = apply | value | block
+//! apply      = argument+
+//! argument   = keyword term
+//! value      = bool | variable | record | update | list | string | money | number | time | duration
+//! money      = number currency
+//! variable   = ident ("." ident)*
+//! block      = "{" block_args? term "}"
+//! block_args = pattern+ "=>"
+//! pattern    = ident | "[" (ident "=" pattern)+ "]" | "[" pattern+ "]"
+//! record     = "[" entry+ "]" | "[" "=" "]"
+//! entry      = ident "=" term
+//! update     = "[" term "with" entry+ "]"
+//! list       = "[" term* "]"
+//! string     = '"' (char | escape | "{" term "}")* '"'
+//! ```
+//!
+//! Comments run from a `#` to the end of the line. They are left out of the tree itself and
+//! attached to the nodes around them as `Trivia` by `attach_comments`.
+//!
+//! A keyword is an identifier immediately followed by a colon. Because the term of an argument
+//! may itself be an `apply`, a keyword following another keyword starts a nested call that takes
+//! every argument after it: `a: b: 1 c: 2` is `a: (b: 1 c: 2)`.
+//!
+//! A script with bindings is a `Root` of its bindings and the term that is its result. A script
+//! without any is just that term.
+//!
+//! A currency is three capital letters, the ISO 4217 code of the currency, like `12.50 USD`.
+//!
+//! Times and durations are written in ISO-8601, like `2024-03-15T09:30:00Z` and `P1DT12H`. A time
+//! without an offset is in UTC.
+//!
+//! The escapes in a string are the same as JSON's, plus `\{` and `\}` for literal braces. A string
+//! with a term in braces is a `Concat` of its literal parts and terms. The lexer keeps the whole
+//! string in a single token, so the terms are lexed and parsed separately once it is reached.
+//!
+//! The parser never stops at a syntax error. It records the error, puts a `Hole` node where a term
+//! was missing, skips whatever it can't make sense of and carries on.
+
+use std::mem;
+
+use id_tree::{InsertBehavior, Node, NodeId, NodeIdError};
+
+use crate::arena::Arena;
+use crate::decimal::Decimal;
+use crate::frontend::lexer::{lex, lex_range, Token, TokenKind};
+use crate::frontend::{NodeData, NodeType, ParseError, SyntaxTree};
+use crate::namespace::NumberMode;
+use crate::primitive::Prim;
+use crate::time;
+
+/// Parse `input` into a tree, returning it along with every syntax error found. Number literals
+/// are interned as `numbers` says.
+pub fn parse_tree<'i>(
+    ns_symbols: &Arena<String>,
+    numbers: NumberMode,
+    input: &'i str,
+) -> Result<(SyntaxTree<'i>, Vec<ParseError<'i>>), NodeIdError> {
+    let mut parser = Parser {
+        tree: SyntaxTree::for_input(ns_symbols, input),
+        numbers,
+        tokens: lex(input)
+            .filter(|token| token.kind != TokenKind::Comment)
+            .collect(),
+        position: 0,
+        last_end: 0,
+        end: input.len(),
+        errors: Vec::new(),
+    };
+    parser.script()?;
+    if let Some(token) = parser.peek() {
+        parser.error_at(&token, format!("unexpected `{}`", token.as_str()));
+    }
+    Ok((parser.tree, parser.errors))
+}
+
+/// Attach every comment in the source of `tree` to the node it describes.
+///
+/// A comment that follows code on the same line trails the outermost node ending just before it.
+/// Other comments lead the outermost node starting just after them. Comments after the last line
+/// of code follow the root.
+pub fn attach_comments(tree: &mut SyntaxTree) -> Result<(), NodeIdError> {
+    let root_id = match tree.nodes.root_node_id() {
+        Some(root_id) => root_id.clone(),
+        None => return Ok(()),
+    };
+    let tokens: Vec<Token> = lex(tree.input).collect();
+    let code = |token: &&Token| token.kind != TokenKind::Comment;
+    for (i, comment) in tokens.iter().enumerate() {
+        if comment.kind != TokenKind::Comment {
+            continue;
+        }
+        let text = String::from(comment.as_str().trim_end());
+        let previous = tokens[..i].iter().rev().find(code);
+        let next = tokens[i + 1..].iter().find(code);
+
+        let same_line =
+            |token: &Token| !tree.input[token.end_pos..comment.start_pos].contains('\n');
+        let trailing_target = match previous {
+            Some(previous) if same_line(previous) => {
+                outermost(tree, &root_id, |data| data.end_pos == previous.end_pos)?
+            }
+            _ => None,
+        };
+        let leading_target = match next {
+            Some(next) => outermost(tree, &root_id, |data| data.start_pos == next.start_pos)?,
+            None => None,
+        };
+
+        if let Some(node_id) = trailing_target {
+            tree.trivia.entry(node_id).or_default().trailing = Some(text);
+        } else if let Some(node_id) = leading_target {
+            tree.trivia.entry(node_id).or_default().leading.push(text);
+        } else if previous.is_some() {
+            tree.trivia
+                .entry(root_id.clone())
+                .or_default()
+                .following
+                .push(text);
+        } else {
+            tree.trivia
+                .entry(root_id.clone())
+                .or_default()
+                .leading
+                .push(text);
+        }
+    }
+    Ok(())
+}
+
+/// The first node in pre-order matching `pred`.
+fn outermost<F>(tree: &SyntaxTree, root_id: &NodeId, pred: F) -> Result<Option<NodeId>, NodeIdError>
+where
+    F: Fn(&NodeData) -> bool,
+{
+    let mut stack = vec![root_id.clone()];
+    while let Some(node_id) = stack.pop() {
+        let node = tree.nodes.get(&node_id)?;
+        if pred(node.data()) {
+            return Ok(Some(node_id));
+        }
+        stack.extend(node.children().iter().rev().cloned());
+    }
+    Ok(None)
+}
+
+struct Parser<'i> {
+    tree: SyntaxTree<'i>,
+    numbers: NumberMode,
+    tokens: Vec<Token<'i>>,
+    position: usize,
+    /// The end of the last token consumed (or hole inserted).
+    last_end: usize,
+    /// The end of the input being parsed, before the closing brace when parsing an interpolation.
+    end: usize,
+    errors: Vec<ParseError<'i>>,
+}
+
+impl<'i> Parser<'i> {
+    fn peek(&self) -> Option<Token<'i>> {
+        self.peek_nth(0)
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<Token<'i>> {
+        self.tokens.get(self.position + n).cloned()
+    }
+
+    fn peek_kind(&self, n: usize) -> Option<TokenKind> {
+        self.peek_nth(n).map(|token| token.kind)
+    }
+
+    fn advance(&mut self) -> Token<'i> {
+        let token = self.tokens[self.position];
+        self.position += 1;
+        self.last_end = token.end_pos;
+        token
+    }
+
+    /// Is the token `n` places ahead the start of a keyword, like `each:`?
+    fn is_keyword(&self, n: usize) -> bool {
+        match (self.peek_nth(n), self.peek_nth(n + 1)) {
+            (Some(word), Some(colon)) => {
+                is_word(word.kind)
+                    && colon.kind == TokenKind::Colon
+                    && colon.start_pos == word.end_pos
+            }
+            _ => false,
+        }
+    }
+
+    /// Is the token `n` places ahead a currency code, like `USD`?
+    fn is_currency(&self, n: usize) -> bool {
+        match self.peek_nth(n) {
+            Some(token) if token.kind == TokenKind::Ident && !self.is_keyword(n) => {
+                let code = token.as_str();
+                code.len() == 3 && code.bytes().all(|b| b.is_ascii_uppercase())
+            }
+            _ => false,
+        }
+    }
+
+    /// Is the token `n` places ahead a name that isn't a keyword?
+    fn is_name(&self, n: usize) -> bool {
+        self.peek_kind(n).is_some_and(is_word) && !self.is_keyword(n)
+    }
+
+    fn error(&mut self, message: String, start_pos: usize, end_pos: usize) {
+        self.errors.push(ParseError::Syntax {
+            input: self.tree.input,
+            message,
+            start_pos,
+            end_pos,
+        });
+    }
+
+    fn error_at(&mut self, token: &Token<'i>, message: String) {
+        self.error(message, token.start_pos, token.end_pos);
+    }
+
+    /// How to describe the last token consumed in an error message.
+    fn previous_text(&self) -> Option<&'i str> {
+        let previous = self.tokens[..self.position].last()?;
+        if previous.kind == TokenKind::Colon && self.position >= 2 {
+            let word = self.tokens[self.position - 2];
+            Some(&self.tree.input[word.start_pos..previous.end_pos])
+        } else {
+            Some(previous.as_str())
+        }
+    }
+
+    fn insert(
+        &mut self,
+        parent: Option<&NodeId>,
+        node_type: NodeType,
+        start_pos: usize,
+        end_pos: usize,
+    ) -> Result<NodeId, NodeIdError> {
+        let insert_as = match parent {
+            Some(parent_id) => InsertBehavior::UnderNode(parent_id),
+            None => InsertBehavior::AsRoot,
+        };
+        let data = NodeData {
+            node_type,
+            start_pos,
+            end_pos,
+        };
+        self.tree.nodes.insert(Node::new(data), insert_as)
+    }
+
+    /// Set the end of a node that was inserted before its children were parsed.
+    fn finish(&mut self, node_id: &NodeId) -> Result<(), NodeIdError> {
+        let end_pos = self.last_end;
+        self.tree.nodes.get_mut(node_id)?.data_mut().end_pos = end_pos;
+        Ok(())
+    }
+
+    fn ident(&mut self, parent: &NodeId) -> Result<(), NodeIdError> {
+        let token = self.advance();
+        let id = self.tree.symbols.intern(token.as_str());
+        self.insert(
+            Some(parent),
+            NodeType::Ident(id),
+            token.start_pos,
+            token.end_pos,
+        )?;
+        Ok(())
+    }
+
+    fn hole(
+        &mut self,
+        parent: Option<&NodeId>,
+        start_pos: usize,
+        end_pos: usize,
+    ) -> Result<(), NodeIdError> {
+        self.insert(parent, NodeType::Hole, start_pos, end_pos)?;
+        self.last_end = end_pos;
+        Ok(())
+    }
+
+    fn term(&mut self, parent: Option<&NodeId>) -> Result<(), NodeIdError> {
+        use self::TokenKind::*;
+
+        let token = match self.peek() {
+            Some(token) => token,
+            None => {
+                let end = self.end;
+                return self.missing_term(parent, end);
+            }
+        };
+        match token.kind {
+            _ if self.is_keyword(0) => self.apply(parent),
+            Ident => self.variable(parent),
+            Bool | Number | Time | Duration => self.primitive(parent),
+            String => self.string(parent),
+            LSquare => self.list_or_record(parent),
+            LCurly => self.block(parent),
+            RSquare | RCurly => self.missing_term(parent, token.start_pos),
+            UnclosedString => {
+                self.advance();
+                self.error_at(&token, "this text is never closed with a `\"`".into());
+                self.hole(parent, token.start_pos, token.end_pos)
+            }
+            Colon | Dot | Arrow | Equals | Comment | Unknown => {
+                self.advance();
+                self.error_at(&token, format!("unexpected `{}`", token.as_str()));
+                self.hole(parent, token.start_pos, token.end_pos)
+            }
+        }
+    }
+
+    fn script(&mut self) -> Result<(), NodeIdError> {
+        if !self.is_binding(0) {
+            return self.term(None);
+        }
+        let start_pos = self.peek().unwrap().start_pos;
+        let root_id = self.insert(None, NodeType::Root, start_pos, start_pos)?;
+        while self.is_binding(0) {
+            let name = self.peek().unwrap();
+            let binding_id = self.insert(
+                Some(&root_id),
+                NodeType::Binding,
+                name.start_pos,
+                name.end_pos,
+            )?;
+            self.ident(&binding_id)?;
+            self.advance();
+            self.term(Some(&binding_id))?;
+            self.finish(&binding_id)?;
+        }
+        if self.peek().is_some() {
+            self.term(Some(&root_id))?;
+        } else {
+            // an error in the last binding already explains why there's nothing after it
+            let end = self.end;
+            if self.errors.is_empty() {
+                self.error(
+                    String::from("expected the result of the script after its bindings"),
+                    end,
+                    end,
+                );
+            }
+            self.hole(Some(&root_id), end, end)?;
+        }
+        self.finish(&root_id)
+    }
+
+    /// Is the token `n` places ahead the start of a binding, like `total =`?
+    fn is_binding(&self, n: usize) -> bool {
+        self.is_name(n) && self.peek_kind(n + 1) == Some(TokenKind::Equals)
+    }
+
+    fn missing_term(&mut self, parent: Option<&NodeId>, pos: usize) -> Result<(), NodeIdError> {
+        let message = match self.previous_text() {
+            Some(previous) => format!("expected a value after `{}`", previous),
+            None => String::from("expected a value"),
+        };
+        self.error(message, pos, pos);
+        self.hole(parent, pos, pos)
+    }
+
+    fn apply(&mut self, parent: Option<&NodeId>) -> Result<(), NodeIdError> {
+        let start_pos = self.peek().unwrap().start_pos;
+        let apply_id = self.insert(parent, NodeType::Apply, start_pos, start_pos)?;
+        while self.is_keyword(0) {
+            let word = self.advance();
+            let colon = self.advance();
+            let arg_id = self.insert(
+                Some(&apply_id),
+                NodeType::Argument,
+                word.start_pos,
+                colon.end_pos,
+            )?;
+            let keyword = self.tree.symbols.intern(word.as_str());
+            self.insert(
+                Some(&arg_id),
+                NodeType::Keyword(keyword),
+                word.start_pos,
+                colon.end_pos,
+            )?;
+            self.term(Some(&arg_id))?;
+            self.finish(&arg_id)?;
+        }
+        self.finish(&apply_id)
+    }
+
+    fn variable(&mut self, parent: Option<&NodeId>) -> Result<(), NodeIdError> {
+        let start_pos = self.peek().unwrap().start_pos;
+        let var_id = self.insert(parent, NodeType::Variable, start_pos, start_pos)?;
+        self.ident(&var_id)?;
+        if self.peek_kind(0) != Some(TokenKind::Dot) {
+            // a variable without a path keeps the whitespace after it, as it always has
+            let rest = &self.tree.input[self.last_end..self.end];
+            let trimmed = rest.trim_start_matches(|c| " \t\r\n".contains(c));
+            self.last_end += rest.len() - trimmed.len();
+        }
+        while self.peek_kind(0) == Some(TokenKind::Dot) {
+            let dot = self.advance();
+            if self.peek_kind(0).is_some_and(is_word) {
+                self.ident(&var_id)?;
+            } else {
+                self.error_at(&dot, "expected a field name after `.`".into());
+                break;
+            }
+        }
+        self.finish(&var_id)
+    }
+
+    fn primitive(&mut self, parent: Option<&NodeId>) -> Result<(), NodeIdError> {
+        let token = self.advance();
+        let text = token.as_str();
+        let node_type = match token.kind {
+            TokenKind::Bool => self.tree.intern_constant(text == "true"),
+            TokenKind::Time | TokenKind::Duration => {
+                let parsed = if token.kind == TokenKind::Time {
+                    time::parse_time(text).map(Prim::Time)
+                } else {
+                    time::parse_duration(text).map(Prim::Duration)
+                };
+                match parsed {
+                    Ok(prim) => self.tree.intern_constant(prim),
+                    Err(message) => {
+                        self.error_at(&token, message);
+                        NodeType::Hole
+                    }
+                }
+            }
+            TokenKind::Number if self.is_currency(0) => {
+                let currency = self.advance();
+                let amount = text.replace('_', "").parse::<Decimal>();
+                let money = amount.map(|amount| Prim::Money(currency.as_str().into(), amount));
+                let node_type = match money {
+                    Ok(money) => self.tree.intern_constant(money),
+                    Err(message) => {
+                        self.error_at(&token, message);
+                        NodeType::Hole
+                    }
+                };
+                self.insert(parent, node_type, token.start_pos, currency.end_pos)?;
+                return Ok(());
+            }
+            _ => {
+                let digits = text.replace('_', "");
+                let number = match self.numbers {
+                    NumberMode::Float => digits.parse::<f64>().map(Prim::Number).ok(),
+                    NumberMode::Decimal => digits.parse::<Decimal>().map(Prim::Decimal).ok(),
+                };
+                match number {
+                    Some(number) => self.tree.intern_constant(number),
+                    None => {
+                        self.error_at(&token, format!("`{}` is not a valid number", text));
+                        NodeType::Hole
+                    }
+                }
+            }
+        };
+        self.insert(parent, node_type, token.start_pos, token.end_pos)?;
+        Ok(())
+    }
+
+    fn string(&mut self, parent: Option<&NodeId>) -> Result<(), NodeIdError> {
+        let token = self.advance();
+        let parts = self.string_parts(&token);
+        let is_text = |part: &StringPart| match *part {
+            StringPart::Text(..) => true,
+            StringPart::Term(..) => false,
+        };
+        if parts.iter().all(is_text) {
+            let text = match parts.into_iter().next() {
+                Some(StringPart::Text(text, _, _)) => text,
+                _ => String::new(),
+            };
+            let node_type = self.tree.intern_constant(text);
+            self.insert(parent, node_type, token.start_pos, token.end_pos)?;
+            return Ok(());
+        }
+
+        let concat_id = self.insert(parent, NodeType::Concat, token.start_pos, token.end_pos)?;
+        for part in parts {
+            match part {
+                StringPart::Text(text, start_pos, end_pos) => {
+                    let node_type = self.tree.intern_constant(text);
+                    self.insert(Some(&concat_id), node_type, start_pos, end_pos)?;
+                }
+                StringPart::Term(start_pos, end_pos) => {
+                    self.interpolation(&concat_id, start_pos, end_pos)?
+                }
+            }
+        }
+        self.last_end = token.end_pos;
+        Ok(())
+    }
+
+    /// Split a string into its decoded literal parts and the spans of the terms in its braces.
+    fn string_parts(&mut self, token: &Token<'i>) -> Vec<StringPart> {
+        let input = self.tree.input;
+        let end = token.end_pos - 1;
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut text_start = token.start_pos + 1;
+        let mut pos = text_start;
+        while pos < end {
+            let ch = input[pos..].chars().next().unwrap();
+            match ch {
+                '\\' => {
+                    let (decoded, len) = self.escape(pos, end);
+                    text.extend(decoded);
+                    pos += len;
+                }
+                '{' => {
+                    if !text.is_empty() {
+                        parts.push(StringPart::Text(mem::take(&mut text), text_start, pos));
+                    }
+                    let close = matching_curly(input, pos + 1, end);
+                    if close == end {
+                        self.error("`{` is never closed".into(), pos, pos + 1);
+                    }
+                    parts.push(StringPart::Term(pos + 1, close));
+                    pos = (close + 1).min(end);
+                    text_start = pos;
+                }
+                _ => {
+                    text.push(ch);
+                    pos += ch.len_utf8();
+                }
+            }
+        }
+        if !text.is_empty() {
+            parts.push(StringPart::Text(text, text_start, end));
+        }
+        parts
+    }
+
+    /// Decode the escape sequence at `pos`, returning the char it stands for and its length.
+    fn escape(&mut self, pos: usize, end: usize) -> (Option<char>, usize) {
+        let rest = &self.tree.input[pos + 1..end];
+        let ch = match rest.chars().next() {
+            Some(ch) => ch,
+            None => return (None, 1),
+        };
+        let decoded = match ch {
+            '"' | '\\' | '/' | '{' | '}' => ch,
+            'b' => '\u{8}',
+            'f' => '\u{c}',
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            'u' => {
+                let hex = rest
+                    .get(1..5)
+                    .filter(|hex| hex.chars().all(|c| c.is_ascii_hexdigit()));
+                let hex = match hex {
+                    Some(hex) => hex,
+                    None => {
+                        self.error("expected four hex digits after `\\u`".into(), pos, pos + 2);
+                        return (None, 2);
+                    }
+                };
+                match std::char::from_u32(u32::from_str_radix(hex, 16).unwrap()) {
+                    Some(decoded) => return (Some(decoded), 6),
+                    None => {
+                        let message = format!("`\\u{}` is not a valid character", hex);
+                        self.error(message, pos, pos + 6);
+                        return (None, 6);
+                    }
+                }
+            }
+            _ => {
+                let len = 1 + ch.len_utf8();
+                let message = format!("unknown escape `{}`", &self.tree.input[pos..pos + len]);
+                self.error(message, pos, pos + len);
+                return (None, len);
+            }
+        };
+        (Some(decoded), 2)
+    }
+
+    /// Parse the term between `start_pos` and `end_pos`, the inside of an interpolation's braces.
+    fn interpolation(
+        &mut self,
+        parent: &NodeId,
+        start_pos: usize,
+        end_pos: usize,
+    ) -> Result<(), NodeIdError> {
+        let tokens = lex_range(self.tree.input, start_pos, end_pos)
+            .filter(|token| token.kind != TokenKind::Comment)
+            .collect();
+        let outer_tokens = mem::replace(&mut self.tokens, tokens);
+        let outer_position = mem::replace(&mut self.position, 0);
+        let outer_end = mem::replace(&mut self.end, end_pos);
+
+        self.term(Some(parent))?;
+        if let Some(token) = self.peek() {
+            self.error_at(&token, format!("unexpected `{}`", token.as_str()));
+        }
+
+        self.tokens = outer_tokens;
+        self.position = outer_position;
+        self.end = outer_end;
+        Ok(())
+    }
+
+    fn list_or_record(&mut self, parent: Option<&NodeId>) -> Result<(), NodeIdError> {
+        let open = self.advance();
+        if self.peek_kind(0) == Some(TokenKind::Equals)
+            && self.peek_kind(1) == Some(TokenKind::RSquare)
+        {
+            self.advance();
+            self.advance();
+            self.insert(parent, NodeType::Record, open.start_pos, self.last_end)?;
+            return Ok(());
+        }
+
+        let mut is_record = self.is_name(0) && self.peek_kind(1) == Some(TokenKind::Equals);
+        let node_type = if is_record {
+            NodeType::Record
+        } else {
+            NodeType::List
+        };
+        let node_id = self.insert(parent, node_type, open.start_pos, open.end_pos)?;
+        while let Some(token) = self.peek() {
+            match token.kind {
+                TokenKind::RSquare | TokenKind::RCurly => break,
+                _ if is_record => self.entry(&node_id)?,
+                // a list of one term followed by `with` is the fields to change in that term
+                _ if self.is_with() && self.tree.nodes.get(&node_id)?.children().len() == 1 => {
+                    self.advance();
+                    self.tree.nodes.get_mut(&node_id)?.data_mut().node_type = NodeType::Update;
+                    is_record = true;
+                }
+                TokenKind::Colon | TokenKind::Dot | TokenKind::Arrow | TokenKind::Equals => {
+                    self.advance();
+                    self.error_at(&token, format!("unexpected `{}`", token.as_str()));
+                }
+                _ => self.term(Some(&node_id))?,
+            }
+        }
+        self.close(&open, TokenKind::RSquare)?;
+        self.finish(&node_id)
+    }
+
+    /// Is the next token the `with` of an update, followed by its first entry?
+    fn is_with(&self) -> bool {
+        self.is_name(0)
+            && self.peek().unwrap().as_str() == "with"
+            && self.is_name(1)
+            && self.peek_kind(2) == Some(TokenKind::Equals)
+    }
+
+    fn entry(&mut self, record_id: &NodeId) -> Result<(), NodeIdError> {
+        let token = self.peek().unwrap();
+        if !(self.is_name(0) && self.peek_kind(1) == Some(TokenKind::Equals)) {
+            let is_name = self.is_name(0);
+            self.advance();
+            let message = if is_name {
+                format!("expected `=` after `{}`", token.as_str())
+            } else {
+                format!("unexpected `{}`", token.as_str())
+            };
+            self.error_at(&token, message);
+            return Ok(());
+        }
+        let entry_id = self.insert(
+            Some(record_id),
+            NodeType::RecordEntry,
+            token.start_pos,
+            token.end_pos,
+        )?;
+        self.ident(&entry_id)?;
+        self.advance();
+        self.term(Some(&entry_id))?;
+        self.finish(&entry_id)
+    }
+
+    fn block(&mut self, parent: Option<&NodeId>) -> Result<(), NodeIdError> {
+        let open = self.advance();
+        let block_id = self.insert(parent, NodeType::Block, open.start_pos, open.end_pos)?;
+
+        let mut arg_count = 0;
+        let mut args_len = 0;
+        while let Some(len) = self.pattern_len(args_len) {
+            arg_count += 1;
+            args_len += len;
+        }
+        if arg_count > 0 && self.peek_kind(args_len) == Some(TokenKind::Arrow) {
+            let start_pos = self.peek().unwrap().start_pos;
+            let args_id =
+                self.insert(Some(&block_id), NodeType::BlockArgs, start_pos, start_pos)?;
+            for _ in 0..arg_count {
+                self.pattern(&args_id)?;
+            }
+            self.advance();
+            self.finish(&args_id)?;
+        }
+
+        self.term(Some(&block_id))?;
+        self.close(&open, TokenKind::RCurly)?;
+        self.finish(&block_id)
+    }
+
+    /// How many tokens the pattern `n` places ahead takes up, if there is one there.
+    fn pattern_len(&self, n: usize) -> Option<usize> {
+        if self.is_name(n) {
+            return Some(1);
+        }
+        if self.peek_kind(n) != Some(TokenKind::LSquare) {
+            return None;
+        }
+        let is_record = self.is_name(n + 1) && self.peek_kind(n + 2) == Some(TokenKind::Equals);
+        let mut len = 1;
+        loop {
+            if len > 1 && self.peek_kind(n + len) == Some(TokenKind::RSquare) {
+                return Some(len + 1);
+            }
+            if is_record {
+                if !(self.is_name(n + len)
+                    && self.peek_kind(n + len + 1) == Some(TokenKind::Equals))
+                {
+                    return None;
+                }
+                len += 2;
+            }
+            len += self.pattern_len(n + len)?;
+        }
+    }
+
+    /// A block argument, which `pattern_len` has already found to be well formed. Record and list
+    /// patterns are `Record` and `List` nodes with an `Ident` for each name they bind.
+    fn pattern(&mut self, parent: &NodeId) -> Result<(), NodeIdError> {
+        if self.peek_kind(0) != Some(TokenKind::LSquare) {
+            return self.ident(parent);
+        }
+        let open = self.advance();
+        let is_record = self.is_name(0) && self.peek_kind(1) == Some(TokenKind::Equals);
+        let node_type = if is_record {
+            NodeType::Record
+        } else {
+            NodeType::List
+        };
+        let node_id = self.insert(Some(parent), node_type, open.start_pos, open.end_pos)?;
+        while self.peek_kind(0) != Some(TokenKind::RSquare) {
+            if is_record {
+                let token = self.peek().unwrap();
+                let entry_id = self.insert(
+                    Some(&node_id),
+                    NodeType::RecordEntry,
+                    token.start_pos,
+                    token.end_pos,
+                )?;
+                self.ident(&entry_id)?;
+                self.advance();
+                self.pattern(&entry_id)?;
+                self.finish(&entry_id)?;
+            } else {
+                self.pattern(&node_id)?;
+            }
+        }
+        self.advance();
+        self.finish(&node_id)
+    }
+
+    /// Consume the bracket closing `open`, reporting and skipping anything before it.
+    fn close(&mut self, open: &Token<'i>, kind: TokenKind) -> Result<(), NodeIdError> {
+        let mut reported = false;
+        let mut depth = 0;
+        while let Some(token) = self.peek() {
+            match token.kind {
+                TokenKind::LSquare | TokenKind::LCurly => depth += 1,
+                TokenKind::RSquare | TokenKind::RCurly if depth > 0 => depth -= 1,
+                _ if token.kind == kind => {
+                    self.advance();
+                    return Ok(());
+                }
+                TokenKind::RSquare | TokenKind::RCurly => break,
+                _ => {}
+            }
+            if !reported {
+                self.error_at(&token, format!("unexpected `{}`", token.as_str()));
+                reported = true;
+            }
+            self.position += 1;
+        }
+        self.error_at(open, format!("`{}` is never closed", open.as_str()));
+        Ok(())
+    }
+}
+
+enum StringPart {
+    /// Decoded text and the span it was written in.
+    Text(String, usize, usize),
+    /// The span of an interpolated term, inside its braces.
+    Term(usize, usize),
+}
+
+/// The position of the `}` closing an interpolation whose term starts at `start`, or `end` if
+/// there isn't one.
+fn matching_curly(input: &str, start: usize, end: usize) -> usize {
+    let mut depth = 0;
+    for token in lex_range(input, start, end) {
+        match token.kind {
+            TokenKind::LCurly => depth += 1,
+            TokenKind::RCurly if depth == 0 => return token.start_pos,
+            TokenKind::RCurly => depth -= 1,
+            _ => {}
+        }
+    }
+    end
+}
+
+/// Identifiers and bools can both be used as names.
+fn is_word(kind: TokenKind) -> bool {
+    kind == TokenKind::Ident || kind == TokenKind::Bool
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::{parse_recovering, Trivia};
+    use crate::test_helpers::*;
+    use crate::typing;
+    use crate::typing::Type;
+
+    /// Flatten a tree into `(depth, kind, start, end)` in pre-order.
+    fn shape(src: &str) -> Vec<(usize, &'static str, usize, usize)> {
+        let (tree, errors) = parse_tree(&Arena::with_capacity(0), NumberMode::Float, src).unwrap();
+        assert!(errors.is_empty(), "{:?}", errors);
+        let mut out = Vec::new();
+        let mut stack = vec![(0, tree.nodes.root_node_id().unwrap().clone())];
+        while let Some((depth, node_id)) = stack.pop() {
+            let node = tree.nodes.get(&node_id).unwrap();
+            let data = node.data();
+            let kind = match data.node_type {
+                NodeType::Apply => "apply",
+                NodeType::Argument => "argument",
+                NodeType::Keyword(_) => "keyword",
+                NodeType::Variable => "variable",
+                NodeType::Ident(_) => "ident",
+                NodeType::Block => "block",
+                NodeType::BlockArgs => "block_args",
+                NodeType::Record => "record",
+                NodeType::RecordEntry => "entry",
+                NodeType::Update => "update",
+                NodeType::List => "list",
+                NodeType::Primitive(_) => "primitive",
+                NodeType::Root => "root",
+                NodeType::Binding => "binding",
+                NodeType::Concat => "concat",
+                NodeType::Hole => "hole",
+            };
+            out.push((depth, kind, data.start_pos, data.end_pos));
+            for child in node.children().iter().rev() {
+                stack.push((depth + 1, child.clone()));
+            }
+        }
+        out
+    }
+
+    fn recover(src: &str) -> (String, Vec<String>) {
+        let (tree, errors) = parse_recovering(&init_namespace(), src);
+        let messages = errors
+            .into_iter()
+            .map(|err| match err {
+                ParseError::Syntax {
+                    message, start_pos, ..
+                } => format!("{} at {}", message, start_pos),
+                other => format!("{}", other),
+            })
+            .collect();
+        (format!("{}", tree), messages)
+    }
+
+    #[test]
+    fn test_parse_variables() {
+        let variables = vec!["foo", "_neato", "foo_bar", "FooBar"];
+        for input in variables.into_iter() {
+            assert_eq!(
+                shape(input),
+                vec![
+                    (0, "variable", 0, input.len()),
+                    (1, "ident", 0, input.len())
+                ]
+            );
+
+            let separators = vec![".", " .", ". ", " . "];
+            for sep in separators.into_iter() {
+                let path = [input, input, input].join(sep);
+                let len = input.len();
+                assert_eq!(
+                    shape(&path),
+                    vec![
+                        (0, "variable", 0, path.len()),
+                        (1, "ident", 0, len),
+                        (1, "ident", len + sep.len(), len * 2 + sep.len()),
+                        (1, "ident", len * 2 + sep.len() * 2, path.len()),
+                    ]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_keyword() {
+        let nodes = shape("foo: x");
+        assert_eq!(nodes[2], (2, "keyword", 0, 4));
+        // keywords have no children, so the next node is the argument's value
+        assert_eq!(nodes[3].0, 2);
+    }
+
+    #[test]
+    fn test_parse_numbers() {
+        let numbers = ["1", "1000", "1.5", "1.6e10", "1.6e-10", "100_000"];
+        for input in numbers.iter() {
+            assert_eq!(shape(input), vec![(0, "primitive", 0, input.len())]);
+        }
+        assert_eq!(shape("-2"), vec![(0, "primitive", 0, 2)]);
+        let tree = parse_with_prelude("100_000");
+        assert_eq!(format!("{}", tree), "100000");
+    }
+
+    #[test]
+    fn test_nested_keywords_take_the_remaining_arguments() {
+        assert_eq!(
+            shape("a: b: 1 c: 2"),
+            vec![
+                (0, "apply", 0, 12),
+                (1, "argument", 0, 12),
+                (2, "keyword", 0, 2),
+                (2, "apply", 3, 12),
+                (3, "argument", 3, 7),
+                (4, "keyword", 3, 5),
+                (4, "primitive", 6, 7),
+                (3, "argument", 8, 12),
+                (4, "keyword", 8, 10),
+                (4, "primitive", 11, 12),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_larger() {
+        let src = "each: offices do: { office => [
+            name = get_name: office
+            employees = each: office.employees do: { e => get_name: e }
+        ] }";
+        assert_eq!(
+            shape(src),
+            vec![
+                (0, "apply", 0, src.len()),
+                (1, "argument", 0, 14),
+                (2, "keyword", 0, 5), // each:
+                (2, "variable", 6, 14),
+                (3, "ident", 6, 13), // offices
+                (1, "argument", 14, src.len()),
+                (2, "keyword", 14, 17), // do:
+                (2, "block", 18, src.len()),
+                (3, "block_args", 20, 29),
+                (4, "ident", 20, 26), // office
+                (3, "record", 30, src.len() - 2),
+                (4, "entry", 44, 80),
+                (5, "ident", 44, 48), // name
+                (5, "apply", 51, 80),
+                (6, "argument", 51, 80),
+                (7, "keyword", 51, 60), // get_name:
+                (7, "variable", 61, 80),
+                (8, "ident", 61, 67), // office
+                (4, "entry", 80, 139),
+                (5, "ident", 80, 89), // employees
+                (5, "apply", 92, 139),
+                (6, "argument", 92, 114),
+                (7, "keyword", 92, 97), // each:
+                (7, "variable", 98, 114),
+                (8, "ident", 98, 104),  // office
+                (8, "ident", 105, 114), // employees
+                (6, "argument", 115, 139),
+                (7, "keyword", 115, 118), // do:
+                (7, "block", 119, 139),
+                (8, "block_args", 121, 125),
+                (9, "ident", 121, 122), // e
+                (8, "apply", 126, 138),
+                (9, "argument", 126, 138),
+                (10, "keyword", 126, 135), // get_name:
+                (10, "variable", 136, 138),
+                (11, "ident", 136, 137), // e
+            ]
+        );
+    }
+
+    #[test]
+    fn test_empty_list_and_record() {
+        assert_eq!(shape("[]"), vec![(0, "list", 0, 2)]);
+        assert_eq!(shape("[ = ]"), vec![(0, "record", 0, 5)]);
+    }
+
+    #[test]
+    fn test_missing_values_become_holes() {
+        assert_eq!(
+            recover("calc: 1 plus:"),
+            (
+                "calc: 1 plus: ?".into(),
+                vec!["expected a value after `plus:` at 13".into()]
+            )
+        );
+        assert_eq!(
+            recover("if: x then:"),
+            (
+                "if: x then: { ? }".into(),
+                vec!["expected a value after `then:` at 11".into()]
+            )
+        );
+        assert_eq!(
+            recover("[ a = 1 b = ]"),
+            (
+                "[a=1 b=?]".into(),
+                vec!["expected a value after `=` at 12".into()]
+            )
+        );
+        assert_eq!(
+            recover("each: xs do: { x => }"),
+            (
+                "each: xs do: { x => ? }".into(),
+                vec!["expected a value after `=>` at 20".into()]
+            )
+        );
+    }
+
+    #[test]
+    fn test_unclosed_and_stray_brackets() {
+        assert_eq!(
+            recover(""),
+            ("?".into(), vec!["expected a value at 0".into()])
+        );
+        assert_eq!(
+            recover("[1 2"),
+            ("[1 2]".into(), vec!["`[` is never closed at 0".into()])
+        );
+        assert_eq!(
+            recover("calc: 1 plus: 2 ]"),
+            (
+                "calc: 1 plus: 2".into(),
+                vec!["unexpected `]` at 16".into()]
+            )
+        );
+        assert_eq!(
+            recover("[ x = { y }"),
+            ("[x={ y }]".into(), vec!["`[` is never closed at 0".into()])
+        );
+    }
+
+    #[test]
+    fn test_skips_unparseable_input_and_reports_every_error() {
+        let (tree, errors) = recover("[ x = @ y = 2 z = ]");
+        assert_eq!(tree, "[x=? y=2 z=?]");
+        assert_eq!(
+            errors,
+            vec!["unexpected `@` at 6", "expected a value after `=` at 18"]
+        );
+
+        let (tree, errors) = recover("[ a = 1 b ]");
+        assert_eq!(tree, "[a=1]");
+        assert_eq!(errors, vec!["expected `=` after `b` at 8"]);
+
+        let (tree, errors) = recover("{ x y }");
+        assert_eq!(tree, "{ x }");
+        assert_eq!(errors, vec!["unexpected `y` at 4"]);
+    }
+
+    #[test]
+    fn test_hole_spans_point_at_original_input() {
+        let src = "calc: 1 plus: @ times: 2";
+        let (tree, errors) = parse_recovering(&init_namespace(), src);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(format!("{}", tree), "calc: 1 plus: ? times: 2");
+        let hole = tree
+            .traverse()
+            .unwrap()
+            .find(|node| node.data().node_type == NodeType::Hole)
+            .unwrap();
+        assert_eq!((hole.data().start_pos, hole.data().end_pos), (14, 15));
+    }
+
+    #[test]
+    fn test_type_checks_the_rest_of_the_script() {
+        let ns = init_namespace();
+        let (tree, errors) = parse_recovering(&ns, "calc: x plus: @ times: \"2\"");
+        assert_eq!(errors.len(), 1);
+        let result = typing::type_of(&ns, vec![], &tree);
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(
+            format!("{}", result.errors[0]),
+            "expected a number, but found a string"
+        );
+        assert_eq!(result.inputs.get("x"), Some(&Type::Num));
+    }
+
+    #[test]
+    fn test_decodes_escapes() {
+        let tree = parse_with_prelude(r#""a\nb\t\"\\\u00e9\{\}""#);
+        let root = tree.nodes.get(tree.nodes.root_node_id().unwrap()).unwrap();
+        match root.data().node_type {
+            NodeType::Primitive(id) => {
+                assert_eq!(tree.lookup_constant(id), &"a\nb\t\"\\\u{e9}{}".into())
+            }
+            other => panic!("expected a string, found {:?}", other),
+        }
+        assert_eq!(format!("{}", tree), r#""a\nb\t\"\\é\{}""#);
+
+        let (_, errors) = recover(r#"["\q" "\u12" "\ud800"]"#);
+        assert_eq!(
+            errors,
+            vec![
+                "unknown escape `\\q` at 2",
+                "expected four hex digits after `\\u` at 7",
+                "`\\ud800` is not a valid character at 14",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_times_and_durations() {
+        let tree = parse_with_prelude("[2024-03-15 2024-03-15T10:30+01:00 P1W PT90M]");
+        assert_eq!(
+            format!("{}", tree),
+            "[2024-03-15T00:00:00Z 2024-03-15T09:30:00Z P7D PT1H30M]"
+        );
+
+        let (tree, errors) = recover("[2024-02-30 P1Y]");
+        assert_eq!(tree, "[? ?]");
+        assert_eq!(
+            errors,
+            vec![
+                "`2024-02-30` is not a valid time, expected something like `2024-03-15` or \
+                 `2024-03-15T09:30:00Z` at 1",
+                "`P1Y` is not a valid duration, expected something like `P1D` or `PT1H30M` at 12",
+            ]
+        );
+
+        // words that aren't whole durations are variables
+        let (tree, errors) = recover("[P1 PT P1D]");
+        assert_eq!(tree, "[P1 PT P1D]");
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[test]
+    fn test_parse_money() {
+        assert_eq!(
+            shape("[12.50 USD -1_000 EUR 3 usd]"),
+            vec![
+                (0, "list", 0, 28),
+                (1, "primitive", 1, 10),
+                (1, "primitive", 11, 21),
+                (1, "primitive", 22, 23),
+                (1, "variable", 24, 27),
+                (2, "ident", 24, 27),
+            ]
+        );
+        let tree = parse_with_prelude("[12.50 USD -1_000 EUR]");
+        assert_eq!(format!("{}", tree), "[12.50 USD -1000 EUR]");
+        assert_eq!(
+            format!("{}", parse_with_prelude("calcMoney: 1 USD plus: 2 USD")),
+            "calcMoney: 1 USD plus: 2 USD"
+        );
+
+        let (tree, errors) = recover("[1e20000000000 USD 1e-4294967297 USD]");
+        assert_eq!(tree, "[? ?]");
+        assert_eq!(
+            errors,
+            vec![
+                "`1e20000000000` is not a valid number at 1",
+                "`1e-4294967297` is not a valid number at 19",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_bindings() {
+        assert_eq!(
+            shape("a = x.y\nb = f: a\n[a b]"),
+            vec![
+                (0, "root", 0, 22),
+                (1, "binding", 0, 7),
+                (2, "ident", 0, 1),
+                (2, "variable", 4, 7),
+                (3, "ident", 4, 5),
+                (3, "ident", 6, 7),
+                (1, "binding", 8, 17),
+                (2, "ident", 8, 9),
+                (2, "apply", 12, 17),
+                (3, "argument", 12, 17),
+                (4, "keyword", 12, 14),
+                (4, "variable", 15, 17),
+                (5, "ident", 15, 16),
+                (1, "list", 17, 22),
+                (2, "variable", 18, 20),
+                (3, "ident", 18, 19),
+                (2, "variable", 20, 21),
+                (3, "ident", 20, 21),
+            ]
+        );
+        assert_eq!(
+            recover("a = 1\nb ="),
+            (
+                "a = 1\nb = ?\n?\n".to_string(),
+                vec!["expected a value after `=` at 9".to_string()]
+            )
+        );
+        assert_eq!(
+            recover("a = 1"),
+            (
+                "a = 1\n?\n".to_string(),
+                vec!["expected the result of the script after its bindings at 5".to_string()]
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_updates() {
+        assert_eq!(
+            shape("[o.x with a = 1 b = c]"),
+            vec![
+                (0, "update", 0, 22),
+                (1, "variable", 1, 4),
+                (2, "ident", 1, 2),
+                (2, "ident", 3, 4),
+                (1, "entry", 10, 15),
+                (2, "ident", 10, 11),
+                (2, "primitive", 14, 15),
+                (1, "entry", 16, 21),
+                (2, "ident", 16, 17),
+                (2, "variable", 20, 21),
+                (3, "ident", 20, 21),
+            ]
+        );
+        // `with` is only special after the first item of a list
+        assert_eq!(
+            format!("{}", parse_with_prelude("[with a with]")),
+            "[with a with]"
+        );
+        assert_eq!(
+            recover("[a b with c = 1]"),
+            (
+                "[a b with c 1]".to_string(),
+                vec!["unexpected `=` at 12".to_string()]
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_block_patterns() {
+        assert_eq!(
+            shape("{ [a = x b = [y z]] c => x }"),
+            vec![
+                (0, "block", 0, 28),
+                (1, "block_args", 2, 24),
+                (2, "record", 2, 19),
+                (3, "entry", 3, 8),
+                (4, "ident", 3, 4),
+                (4, "ident", 7, 8),
+                (3, "entry", 9, 18),
+                (4, "ident", 9, 10),
+                (4, "list", 13, 18),
+                (5, "ident", 14, 15),
+                (5, "ident", 16, 17),
+                (2, "ident", 20, 21),
+                (1, "variable", 25, 27),
+                (2, "ident", 25, 26),
+            ]
+        );
+        // without an arrow, brackets are still a value
+        assert_eq!(
+            format!("{}", parse_with_prelude("{ [a = x] }")),
+            "{ [a=x] }"
+        );
+    }
+
+    #[test]
+    fn test_parse_interpolation() {
+        let src = r#""Hello {user.name}, {calc: 1 plus: "2"}!""#;
+        assert_eq!(
+            shape(src),
+            vec![
+                (0, "concat", 0, src.len()),
+                (1, "primitive", 1, 7),
+                (1, "variable", 8, 17),
+                (2, "ident", 8, 12),
+                (2, "ident", 13, 17),
+                (1, "primitive", 18, 20),
+                (1, "apply", 21, 38),
+                (2, "argument", 21, 28),
+                (3, "keyword", 21, 26),
+                (3, "primitive", 27, 28),
+                (2, "argument", 29, 38),
+                (3, "keyword", 29, 34),
+                (3, "primitive", 35, 38),
+                (1, "primitive", 39, 40),
+            ]
+        );
+        let tree = parse_with_prelude(src);
+        assert_eq!(format!("{}", tree), src);
+
+        assert_eq!(
+            recover(r#""a {} b { x y }""#),
+            (
+                r#""a {?} b {x}""#.into(),
+                vec![
+                    "expected a value at 4".into(),
+                    "unexpected `y` at 12".into()
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn test_comments_attach_to_adjacent_nodes() {
+        let src = "[\n  # the name\n  name = \"x\" # always x\n  age = 1\n]\n# end";
+        let tree = parse_with_prelude(src);
+        let mut attached: Vec<(String, Trivia)> = tree
+            .trivia
+            .iter()
+            .map(|(node_id, trivia)| {
+                (
+                    String::from(tree.node_id_str(node_id).unwrap()),
+                    trivia.clone(),
+                )
+            })
+            .collect();
+        attached.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            attached,
+            vec![
+                (
+                    String::from(&src[..src.len() - 6]),
+                    Trivia {
+                        following: vec!["# end".into()],
+                        ..Trivia::default()
+                    }
+                ),
+                (
+                    String::from("name = \"x\""),
+                    Trivia {
+                        leading: vec!["# the name".into()],
+                        trailing: Some("# always x".into()),
+                        following: vec![],
+                    }
+                ),
+            ]
+        );
+    }
+}
@@ -1,17 +1,29 @@
+use std::collections::HashMap;
 use std::fmt;
 
 use id_tree::{InsertBehavior, Node, NodeId, NodeIdError, PreOrderTraversal, Tree, TreeBuilder};
-use pest::iterators::Pair;
 
 use crate::arena::*;
-use crate::frontend::grammar::Rule;
-use crate::primitive::Prim;
+use crate::primitive::{escape, Prim};
 
 pub struct SyntaxTree<'i> {
     pub input: &'i str,
     pub nodes: Tree<NodeData>,
     pub constants: Arena<Prim>,
     pub symbols: Arena<String>,
+    /// Comments in the source, attached to the nodes they describe.
+    pub trivia: HashMap<NodeId, Trivia>,
+}
+
+/// The comments attached to a node.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Trivia {
+    /// Comments on their own lines before the node.
+    pub leading: Vec<String>,
+    /// A comment after the node, on the line where it ends.
+    pub trailing: Option<String>,
+    /// Comments on their own lines after the node, only the root has these.
+    pub following: Vec<String>,
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -25,6 +37,7 @@ pub type AstNode = Node<NodeData>;
 
 #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum NodeType {
+    /// A script with bindings, its children are the bindings and the term that is its result.
     Root,
     Primitive(ArenaId),
     Ident(ArenaId),
@@ -32,37 +45,52 @@ pub enum NodeType {
     List,
     Record,
     RecordEntry,
+    /// A record with some fields replaced or added, its children are the record and a
+    /// `RecordEntry` for each field.
+    Update,
     Apply,
     Argument,
     Keyword(ArenaId),
     Block,
     BlockArgs,
+    /// A string with interpolated terms, its children are the literal parts and terms in order.
+    Concat,
+    /// A name bound to the value of a term for the rest of the script, its children are the name
+    /// and the term.
+    Binding,
+    /// A term that could not be parsed, only present in trees from `parse_recovering`.
+    Hole,
 }
 
 impl<'i> SyntaxTree<'i> {
-    pub fn from_input_and_pair(
-        ns_symbols: &Arena<String>,
-        input: &'i str,
-        pair: Pair<'i, Rule>,
-    ) -> Result<Self, NodeIdError> {
+    /// A tree consisting of a single hole spanning the whole input.
+    pub fn hole(ns_symbols: &Arena<String>, input: &'i str) -> Self {
         let mut tree = SyntaxTree::for_input(ns_symbols, input);
-        tree.consume_pair(pair, InsertBehavior::AsRoot)?;
-        Ok(tree)
+        let data = NodeData {
+            node_type: NodeType::Hole,
+            start_pos: 0,
+            end_pos: input.len(),
+        };
+        tree.nodes
+            .insert(Node::new(data), InsertBehavior::AsRoot)
+            .unwrap();
+        tree
     }
 
-    fn for_input(ns_symbols: &Arena<String>, input: &'i str) -> Self {
+    pub fn for_input(ns_symbols: &Arena<String>, input: &'i str) -> Self {
         let node_cap = input.len() / 4;
         let const_cap = input.len() / 16;
         SyntaxTree {
-            input: input,
+            input,
             nodes: TreeBuilder::new().with_node_capacity(node_cap).build(),
             constants: Arena::with_capacity(const_cap),
             symbols: ns_symbols.clone(),
+            trivia: HashMap::new(),
         }
     }
 
     #[inline]
-    fn intern_constant<T: Into<Prim>>(&mut self, c: T) -> NodeType {
+    pub fn intern_constant<T: Into<Prim>>(&mut self, c: T) -> NodeType {
         NodeType::Primitive(self.constants.intern(c.into()))
     }
 
@@ -114,69 +142,17 @@ impl<'i> SyntaxTree<'i> {
     }
     */
 
-    fn consume_pair(
-        &mut self,
-        pair: Pair<'i, Rule>,
-        insert_as: InsertBehavior,
-    ) -> Result<(), NodeIdError> {
-        use self::InsertBehavior::UnderNode;
-        use self::NodeType::*;
-
-        let node_type = match pair.as_rule() {
-            Rule::apply => Apply,
-            Rule::argument => Argument,
-            Rule::variable => Variable,
-            Rule::list => List,
-            Rule::ident => Ident(self.symbols.intern(pair.as_str())),
-            Rule::keyword => Keyword(self.symbols.intern({
-                let s = pair.as_str();
-                &s[0..s.len() - 1]
-            })),
-            Rule::record => Record,
-            Rule::entry => RecordEntry,
-            Rule::block => Block,
-            Rule::block_args => BlockArgs,
-
-            Rule::string => {
-                let mut s = pair.as_str();
-                s = &s[1..s.len() - 1];
-                self.intern_constant(String::from(s))
-            }
-
-            Rule::bool => match pair.as_str() {
-                "true" => self.intern_constant(true),
-                "false" => self.intern_constant(false),
-                _ => panic!(format!("grammar rule `bool` rule matched {:?}", pair)),
-            },
-
-            Rule::number => {
-                let n: f64 = pair.as_str().parse().unwrap();
-                self.intern_constant(n)
-            }
-            rule => panic!("can't treeify {:?}", rule),
-        };
-
-        let node_data = {
-            let span = pair.clone().into_span();
-            NodeData {
-                node_type: node_type,
-                start_pos: span.start(),
-                end_pos: span.end(),
-            }
-        };
-        let node_id = self.nodes.insert(Node::new(node_data), insert_as)?;
-        for inner in pair.into_inner() {
-            self.consume_pair(inner, UnderNode(&node_id))?;
-        }
-        Ok(())
-    }
-
     fn print_node(&self, f: &mut fmt::Formatter, node_id: &NodeId) -> fmt::Result {
         use self::NodeType::*;
         use std::fmt::Write;
 
         let node = self.nodes.get(node_id).unwrap();
         let data = node.data();
+        let trivia = self.trivia.get(node_id);
+
+        for comment in trivia.iter().flat_map(|trivia| &trivia.leading) {
+            writeln!(f, "{}", comment)?;
+        }
 
         match data.node_type {
             Root => {
@@ -187,7 +163,7 @@ impl<'i> SyntaxTree<'i> {
             }
             Primitive(id) => write!(f, "{}", self.constants.resolve(id))?,
             Variable => {
-                for (i, child) in node.children().into_iter().enumerate() {
+                for (i, child) in node.children().iter().enumerate() {
                     if i != 0 {
                         f.write_char('.')?;
                     }
@@ -198,7 +174,7 @@ impl<'i> SyntaxTree<'i> {
             Keyword(id) => write!(f, "{}:", self.symbols.resolve(id))?,
             List => {
                 f.write_char('[')?;
-                for (i, child) in node.children().into_iter().enumerate() {
+                for (i, child) in node.children().iter().enumerate() {
                     if i != 0 {
                         f.write_char(' ')?;
                     }
@@ -208,7 +184,20 @@ impl<'i> SyntaxTree<'i> {
             }
             Record => {
                 f.write_char('[')?;
-                for (i, child) in node.children().into_iter().enumerate() {
+                for (i, child) in node.children().iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(' ')?;
+                    }
+                    self.print_node(f, child)?;
+                }
+                f.write_char(']')?;
+            }
+            Update => {
+                f.write_char('[')?;
+                for (i, child) in node.children().iter().enumerate() {
+                    if i == 1 {
+                        f.write_str(" with")?;
+                    }
                     if i > 0 {
                         f.write_char(' ')?;
                     }
@@ -223,7 +212,7 @@ impl<'i> SyntaxTree<'i> {
                 self.print_node(f, &children[1])?;
             }
             Apply | Argument => {
-                for (i, child) in node.children().into_iter().enumerate() {
+                for (i, child) in node.children().iter().enumerate() {
                     if i > 0 {
                         f.write_char(' ')?;
                     }
@@ -239,7 +228,7 @@ impl<'i> SyntaxTree<'i> {
             }
             BlockArgs => {
                 let children = node.children();
-                if children.len() > 0 {
+                if !children.is_empty() {
                     for child in children {
                         self.print_node(f, child)?;
                         f.write_char(' ')?;
@@ -247,6 +236,41 @@ impl<'i> SyntaxTree<'i> {
                     write!(f, "=> ")?;
                 }
             }
+            Concat => {
+                f.write_char('"')?;
+                for child in node.children() {
+                    let child_type = self.nodes.get(child).unwrap().data().node_type;
+                    match child_type {
+                        Primitive(id) => match *self.constants.resolve(id) {
+                            Prim::String(ref text) => f.write_str(&escape(text))?,
+                            ref other => write!(f, "{{{}}}", other)?,
+                        },
+                        _ => {
+                            f.write_char('{')?;
+                            self.print_node(f, child)?;
+                            f.write_char('}')?;
+                        }
+                    }
+                }
+                f.write_char('"')?;
+            }
+            Binding => {
+                let children = node.children();
+                self.print_node(f, &children[0])?;
+                f.write_str(" = ")?;
+                self.print_node(f, &children[1])?;
+            }
+            Hole => f.write_char('?')?,
+        }
+        if let Some(trivia) = trivia {
+            if let Some(ref comment) = trivia.trailing {
+                writeln!(f, " {}", comment)?;
+            } else if !trivia.following.is_empty() {
+                f.write_char('\n')?;
+            }
+            for comment in &trivia.following {
+                writeln!(f, "{}", comment)?;
+            }
         }
         Ok(())
     }
@@ -274,3 +298,24 @@ fn test_print_tree() {
 
     assert_eq!(format!("{}", tree), "each: offices do: { office => [name=try: { office.name } else: { stringify: office.id } employees=each: office.employees do: { e => upperCase: e.name }] }");
 }
+
+#[test]
+fn test_print_comments() {
+    use crate::test_helpers::*;
+    let src = "# doubles every number
+each: xs # the numbers
+  do: { x => calc: x # left
+    times: 2 }
+# done";
+    let printed = format!("{}", parse_with_prelude(src));
+    assert_eq!(
+        printed,
+        "# doubles every number
+each: xs # the numbers
+ do: { x => calc: x # left
+ times: 2 }
+# done
+"
+    );
+    assert_eq!(format!("{}", parse_with_prelude(&printed)), printed);
+}
@@ -2,6 +2,7 @@ use crate::apply::Apply;
 use crate::arena::ArenaId;
 use crate::arena::*;
 use crate::interpreter::{Machine, Value};
+use crate::namespace::Callback;
 use crate::signature::{Argument, Signature};
 use crate::typing::Type;
 
@@ -9,13 +10,13 @@ pub struct FunctionBuilder<'a, V: Value> {
     symbols: &'a mut Arena<String>,
     signature: Signature<ArenaId>,
     return_type: Option<Type>,
-    callback: Option<Box<Fn(Apply<V>, &mut Machine<V>) -> Result<V, V::Error>>>,
+    callback: Option<Callback<V>>,
 }
 
 impl<'a, V: Value> FunctionBuilder<'a, V> {
     pub fn new(symbols: &'a mut Arena<String>) -> Self {
         FunctionBuilder {
-            symbols: symbols,
+            symbols,
             signature: Signature::with_capacity(4),
             return_type: None,
             callback: None,
@@ -29,6 +30,7 @@ impl<'a, V: Value> FunctionBuilder<'a, V> {
             ty: ty.clone(),
             variadic: false,
             required: true,
+            partial: false,
         });
         name_id
     }
@@ -40,6 +42,7 @@ impl<'a, V: Value> FunctionBuilder<'a, V> {
             ty: ty.clone(),
             variadic: false,
             required: false,
+            partial: false,
         });
         name_id
     }
@@ -51,6 +54,7 @@ impl<'a, V: Value> FunctionBuilder<'a, V> {
             ty: ty.clone(),
             variadic: true,
             required: false,
+            partial: false,
         });
         name_id
     }
@@ -62,6 +66,7 @@ impl<'a, V: Value> FunctionBuilder<'a, V> {
             ty: ty.clone(),
             variadic: true,
             required: true,
+            partial: false,
         });
         name_id
     }
@@ -70,6 +75,8 @@ impl<'a, V: Value> FunctionBuilder<'a, V> {
         self.return_type = Some(ty);
     }
 
+    /// Mark a function that may fail to return a value, so calls to it must be inside `try:`.
+    /// Functions are total unless they are marked partial.
     pub fn is_partial(&mut self) {
         self.signature.set_total(false);
     }
@@ -78,6 +85,17 @@ impl<'a, V: Value> FunctionBuilder<'a, V> {
         self.signature.set_total(true);
     }
 
+    /// Mark a call as partial only when the given argument is supplied.
+    pub fn is_partial_when(&mut self, arg: ArenaId) {
+        self.signature.set_partial_arg(arg);
+    }
+
+    /// Mark a function that calls the block in its second argument named by the case of the union
+    /// in its first, like `match:`, so that calls to it are checked to cover every case.
+    pub fn matches_cases(&mut self) {
+        self.signature.set_matches_cases(true);
+    }
+
     pub fn callback<F>(&mut self, cb: F)
     where
         F: 'static + Fn(Apply<V>, &mut Machine<V>) -> Result<V, V::Error>,
@@ -85,15 +103,7 @@ impl<'a, V: Value> FunctionBuilder<'a, V> {
         self.callback = Some(Box::new(cb));
     }
 
-    pub fn into_parts(
-        self,
-    ) -> Result<
-        (
-            Signature,
-            Box<Fn(Apply<V>, &mut Machine<V>) -> Result<V, V::Error>>,
-        ),
-        String,
-    > {
+    pub fn into_parts(self) -> Result<(Signature, Callback<V>), String> {
         let mut signature = self.signature;
 
         let returns = self.return_type.ok_or(format!(
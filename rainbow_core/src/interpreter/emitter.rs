@@ -20,7 +20,7 @@ struct Emitter<'t> {
 impl<'t> Emitter<'t> {
     pub fn new(tree: &'t SyntaxTree) -> Self {
         Emitter {
-            tree: tree,
+            tree,
             instructions: Vec::with_capacity(1024),
         }
     }
@@ -38,7 +38,7 @@ impl<'t> Emitter<'t> {
                 }
             }
             Primitive(id) => {
-                self.instructions.push(PushPrimitive { id: id });
+                self.instructions.push(PushPrimitive { id });
             }
             List => {
                 let children = node.children();
@@ -57,20 +57,36 @@ impl<'t> Emitter<'t> {
                     let name_and_value_ids: Vec<_> =
                         self.tree.nodes.children_ids(entry_id).unwrap().collect();
 
-                    let field_name = self.tree.node_id_to_symbol_id(&name_and_value_ids[0])?;
+                    let field_name = self.tree.node_id_to_symbol_id(name_and_value_ids[0])?;
                     self.instructions.push(PushKeyword { id: field_name });
-                    self.recur(&name_and_value_ids[1])?;
+                    self.recur(name_and_value_ids[1])?;
                 }
                 self.instructions.push(MkRecord { size: size as u16 });
             }
 
+            Update => {
+                let children = node.children();
+                self.recur(&children[0])?;
+                for entry_id in &children[1..] {
+                    let name_and_value_ids: Vec<_> =
+                        self.tree.nodes.children_ids(entry_id).unwrap().collect();
+
+                    let field_name = self.tree.node_id_to_symbol_id(name_and_value_ids[0])?;
+                    self.instructions.push(PushKeyword { id: field_name });
+                    self.recur(name_and_value_ids[1])?;
+                }
+                self.instructions.push(UpdateRecord {
+                    size: (children.len() - 1) as u16,
+                });
+            }
+
             Variable => {
                 let children = node.children();
                 let root_name = self.tree.node_id_to_symbol_id(&children[0])?;
                 self.instructions.push(PushVar { id: root_name });
 
                 for child_id in children[1..].iter() {
-                    let prop_name = self.tree.node_id_to_symbol_id(&child_id)?;
+                    let prop_name = self.tree.node_id_to_symbol_id(child_id)?;
                     self.instructions.push(PushProp { id: prop_name });
                 }
             }
@@ -84,16 +100,15 @@ impl<'t> Emitter<'t> {
                     let arg_node_ids = self.tree.nodes.get(&children[0])?.children();
                     argc = arg_node_ids.len() as u8;
                     for arg_node_id in arg_node_ids {
-                        let arg_name = self.tree.node_id_to_symbol_id(arg_node_id)?;
-                        self.instructions.push(Bind { id: arg_name });
+                        self.bind_pattern(arg_node_id)?;
                     }
                 }
-                if children.len() > 0 {
+                if !children.is_empty() {
                     self.recur(&children[children.len() - 1])?;
                 }
                 let skip = self.instructions.len() - (jump_ip + 1);
                 self.instructions[jump_ip] = MkBlock {
-                    argc: argc,
+                    argc,
                     skip: skip as u16,
                 };
             }
@@ -101,7 +116,7 @@ impl<'t> Emitter<'t> {
             Apply => {
                 let children = node.children();
                 for child_id in children.iter() {
-                    let arg_children = self.tree.nodes.get(&child_id)?.children();
+                    let arg_children = self.tree.nodes.get(child_id)?.children();
                     let arg_name = self.tree.node_id_to_symbol_id(&arg_children[0])?;
                     self.instructions.push(PushKeyword { id: arg_name });
                     self.recur(&arg_children[1])?;
@@ -110,10 +125,62 @@ impl<'t> Emitter<'t> {
                     argc: children.len() as u16,
                 });
             }
+            Binding => {
+                let children = node.children();
+                self.recur(&children[1])?;
+                let name = self.tree.node_id_to_symbol_id(&children[0])?;
+                self.instructions.push(Bind { id: name });
+            }
+            Concat => {
+                let children = node.children();
+                for child_id in children {
+                    self.recur(child_id)?;
+                }
+                self.instructions.push(MkString {
+                    count: children.len() as u16,
+                });
+            }
             // other node types won't be visited, and should emit no instructions
             _ => {}
         }
-        return Ok(());
+        Ok(())
+    }
+
+    /// Bind the names in a block argument to the parts of the value on top of the stack.
+    fn bind_pattern(&mut self, node_id: &NodeId) -> Result<(), NodeIdError> {
+        use super::Instruction::*;
+        use crate::frontend::NodeType::*;
+        let node = self.tree.nodes.get(node_id)?;
+        let children = node.children();
+        match node.data().node_type {
+            Record => {
+                for (i, entry_id) in children.iter().enumerate() {
+                    let name_and_pattern_ids: Vec<_> =
+                        self.tree.nodes.children_ids(entry_id)?.collect();
+                    // every part but the last takes a copy, the last one takes the record itself
+                    if i + 1 < children.len() {
+                        self.instructions.push(Dup);
+                    }
+                    let field_name = self.tree.node_id_to_symbol_id(name_and_pattern_ids[0])?;
+                    self.instructions.push(PushProp { id: field_name });
+                    self.bind_pattern(name_and_pattern_ids[1])?;
+                }
+            }
+            List => {
+                for (i, elem_id) in children.iter().enumerate() {
+                    if i + 1 < children.len() {
+                        self.instructions.push(Dup);
+                    }
+                    self.instructions.push(PushItem { index: i as u16 });
+                    self.bind_pattern(elem_id)?;
+                }
+            }
+            _ => {
+                let name = self.tree.node_id_to_symbol_id(node_id)?;
+                self.instructions.push(Bind { id: name });
+            }
+        }
+        Ok(())
     }
 }
 
@@ -177,6 +244,24 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_emit_update() {
+        use crate::test_helpers::*;
+        let tree = parse_with_prelude("[ x with y = 3 ]");
+        let instructions = emit(&tree).unwrap();
+        let x_id = tree.symbols.find(&"x").unwrap();
+        let y_id = tree.symbols.find(&"y").unwrap();
+        assert_eq!(
+            instructions,
+            vec![
+                PushVar { id: x_id },
+                PushKeyword { id: y_id },
+                PushPrimitive { id: 0 },
+                UpdateRecord { size: 1 },
+            ]
+        );
+    }
+
     #[test]
     fn test_emit_block() {
         use crate::test_helpers::*;
@@ -197,6 +282,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_emit_block_patterns() {
+        use crate::test_helpers::*;
+        let tree = parse_with_prelude("{ [x = a y = [b c]] => a }");
+        let instructions = emit(&tree).unwrap();
+        let id = |name: &str| tree.symbols.find(&name).unwrap();
+        assert_eq!(
+            instructions,
+            vec![
+                MkBlock { argc: 1, skip: 10 },
+                Dup,
+                PushProp { id: id("x") },
+                Bind { id: id("a") },
+                PushProp { id: id("y") },
+                Dup,
+                PushItem { index: 0 },
+                Bind { id: id("b") },
+                PushItem { index: 1 },
+                Bind { id: id("c") },
+                PushVar { id: id("a") },
+            ]
+        );
+    }
+
     #[test]
     fn test_emit_function_call() {
         use crate::test_helpers::*;
@@ -12,6 +12,12 @@ pub enum Instruction {
     PushProp {
         id: ArenaId,
     },
+    /// Replace the list on top of the values stack with its item at `index`
+    PushItem {
+        index: u16,
+    },
+    /// Push a copy of the value on top of the values stack
+    Dup,
     PushKeyword {
         id: ArenaId,
     },
@@ -21,6 +27,10 @@ pub enum Instruction {
     MkRecord {
         size: u16,
     },
+    /// Copy the record below the fields on top of the values stack, replacing or adding those fields
+    UpdateRecord {
+        size: u16,
+    },
     MkBlock {
         argc: u8,
         skip: u16,
@@ -31,4 +41,8 @@ pub enum Instruction {
     CallFunction {
         argc: u16,
     },
+    /// Join the strings, numbers and booleans on top of the values stack into one string
+    MkString {
+        count: u16,
+    },
 }
@@ -29,12 +29,12 @@ impl<'a, V: Value + 'a> Machine<'a, V> {
         bindings: Vec<(u16, V)>,
     ) -> Self {
         Machine {
-            ns: ns,
-            instructions: instructions,
+            ns,
+            instructions,
             instruction_pointer: 0,
-            program_data: program_data,
-            symbols: symbols,
-            bindings: bindings,
+            program_data,
+            symbols,
+            bindings,
             value_stack: Vec::with_capacity(128),
             keyword_stack: Vec::with_capacity(32),
         }
@@ -88,9 +88,10 @@ impl<'a, V: Value + 'a> Machine<'a, V> {
 
         self.value_stack.extend(args);
         self.instruction_pointer = block.ip as usize;
-        self.eval_range(block.ip as usize, block.size as usize)?;
-
-        let result = self.pop_value();
+        // the stacks are restored even if the block fails, so that `try:` can carry on after it
+        let result = self
+            .eval_range(block.ip as usize, block.size as usize)
+            .and_then(|_| self.pop_value());
 
         self.instruction_pointer = orig_ip;
         self.value_stack.truncate(orig_value_stack_size);
@@ -134,6 +135,26 @@ impl<'a, V: Value + 'a> Machine<'a, V> {
                     .ok_or_else(|| self.error(MachineError::Undefined))?;
                 self.value_stack.push(value);
             }
+            PushItem { index } => {
+                use crate::interpreter::List;
+                let list = self
+                    .value_stack
+                    .pop()
+                    .ok_or_else(|| self.error(MachineError::ValueStackEmpty))?
+                    .try_list()?;
+                let value = list
+                    .at(index as usize)
+                    .ok_or_else(|| self.error(MachineError::Undefined))?;
+                self.value_stack.push(value);
+            }
+            Dup => {
+                let value = self
+                    .value_stack
+                    .last()
+                    .cloned()
+                    .ok_or_else(|| self.error(MachineError::ValueStackEmpty))?;
+                self.value_stack.push(value);
+            }
             PushKeyword { id } => {
                 self.keyword_stack.push(id);
             }
@@ -151,11 +172,27 @@ impl<'a, V: Value + 'a> Machine<'a, V> {
                 };
                 self.value_stack.push(value);
             }
+            UpdateRecord { size } => {
+                let value = {
+                    let fields: Vec<(String, V)> = self
+                        .pop_pairs(size)?
+                        .into_iter()
+                        .map(|(sym_id, val)| (self.symbols[sym_id as usize].clone(), val))
+                        .collect();
+                    let record = self.pop_value()?.try_record()?;
+                    let kept: Vec<(String, V)> = record
+                        .into_iter()
+                        .filter(|(name, _)| !fields.iter().any(|(field, _)| field == name))
+                        .collect();
+                    V::from_iter(kept.into_iter().chain(fields))
+                };
+                self.value_stack.push(value);
+            }
             MkBlock { argc, skip } => {
                 let block = Block {
                     machine: (self as *mut Self as usize),
                     ip: (self.instruction_pointer + 1) as u32,
-                    argc: argc,
+                    argc,
                     size: skip,
                 };
                 self.instruction_pointer += skip as usize;
@@ -169,7 +206,7 @@ impl<'a, V: Value + 'a> Machine<'a, V> {
                 use crate::apply::Apply;
                 let apply = Apply::from(self.pop_pairs(argc)?);
                 let value = {
-                    let func_id = apply.func_id().clone();
+                    let func_id = *apply.func_id();
                     let callback = self.ns.get_callback(&func_id).ok_or_else(|| {
                         format!("Function `{}` is undefined", self.symbols[func_id as usize])
                     })?;
@@ -177,6 +214,25 @@ impl<'a, V: Value + 'a> Machine<'a, V> {
                 };
                 self.value_stack.push(value);
             }
+            MkString { count } => {
+                let mut joined = String::new();
+                for value in self.pop_values(count)? {
+                    if let Ok(s) = value.try_string() {
+                        joined.push_str(s);
+                    } else if let Ok(d) = value.try_decimal() {
+                        joined.push_str(&d.to_string());
+                    } else if let Ok(b) = value.try_bool() {
+                        joined.push_str(&b.to_string());
+                    } else {
+                        return Err(V::Error::from(format!(
+                            "only strings, numbers and true or false can be put in a string, but \
+                             this is {}",
+                            describe_value(&value)
+                        )));
+                    }
+                }
+                self.value_stack.push(V::from(joined));
+            }
         }
         self.instruction_pointer += 1;
         Ok(())
@@ -215,12 +271,33 @@ impl<'a, V: Value + 'a> Machine<'a, V> {
     }
 }
 
+/// Describe a value that isn't a string, number or boolean, the way type errors describe types.
+fn describe_value<V: Value>(value: &V) -> &'static str {
+    if value.try_time().is_ok() {
+        "a time"
+    } else if value.try_duration().is_ok() {
+        "a length of time"
+    } else if value.try_money().is_ok() {
+        "an amount of money"
+    } else if value.try_list().is_ok() {
+        "a list"
+    } else if value.try_record().is_ok() {
+        "a record"
+    } else if value.callable() {
+        "a block"
+    } else {
+        "some other value"
+    }
+}
+
 fn box_prim<V: Value>(prim: &Prim) -> V {
     match *prim {
         Prim::Number(n) => V::from(n),
+        Prim::Decimal(ref d) => V::from(d.clone()),
         Prim::String(ref s) => V::from(s.clone()),
         Prim::Boolean(b) => V::from(b),
         Prim::Time(i) => V::from(i),
-        Prim::Money(_, _n) => panic!("no money"),
+        Prim::Duration(d) => V::from(d),
+        Prim::Money(ref currency, ref amount) => V::from((currency.clone(), amount.clone())),
     }
 }
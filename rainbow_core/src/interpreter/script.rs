@@ -6,7 +6,6 @@ use crate::interpreter::{emitter, Instruction, Value};
 use crate::namespace;
 use crate::typing; //::{type_of, Type, TypeError};
 use id_tree;
-use pest;
 
 pub struct Script<'i, V: Value> {
     pub ns: namespace::SharedNamespace<V>,
@@ -27,8 +26,37 @@ pub enum Stage {
 
 #[derive(Debug)]
 pub enum CompileError<'i> {
-    ParseError(pest::Error<'i, frontend::Rule>),
+    ParseError(frontend::ParseError<'i>),
     NodeIdError(Stage, id_tree::NodeIdError),
+    TypeErrors(Vec<typing::TypeError>),
+}
+
+/// Options that control how strictly `Script::compile_with` treats its input.
+#[derive(Debug, Clone, Default)]
+pub struct CompileOptions {
+    /// Build a runnable script even when type checking finds errors. Only interactive tools like
+    /// the REPL should need this, the errors are still available in `Script::typer_result`.
+    pub lenient: bool,
+    /// Types of the inputs the host will provide. Uses of these inputs are checked against the
+    /// declared type instead of being inferred.
+    pub globals: collections::HashMap<String, typing::Type>,
+}
+
+impl CompileOptions {
+    pub fn strict() -> Self {
+        CompileOptions::default()
+    }
+
+    pub fn lenient() -> Self {
+        CompileOptions {
+            lenient: true,
+            ..CompileOptions::default()
+        }
+    }
+
+    pub fn with_globals(self, globals: collections::HashMap<String, typing::Type>) -> Self {
+        CompileOptions { globals, ..self }
+    }
 }
 
 impl<'i> From<frontend::ParseError<'i>> for CompileError<'i> {
@@ -36,7 +64,7 @@ impl<'i> From<frontend::ParseError<'i>> for CompileError<'i> {
         use crate::frontend::ParseError::*;
         match err {
             NodeId(err) => CompileError::NodeIdError(Stage::Parse, err),
-            Pest(err) => CompileError::ParseError(err),
+            err => CompileError::ParseError(err),
         }
     }
 }
@@ -47,33 +75,101 @@ impl<'i> fmt::Display for CompileError<'i> {
         match *self {
             NodeIdError(stage, ref _err) => write!(f, "Internal compiler error stage={:?}", stage),
             ParseError(ref err) => write!(f, "{}", err),
+            TypeErrors(ref errors) => {
+                write!(f, "{} type error(s):", errors.len())?;
+                for err in errors {
+                    write!(f, "\n  {}", err)?;
+                }
+                Ok(())
+            }
         }
     }
 }
 
 impl<'i, V: Value> Script<'i, V> {
+    /// Compile `src`, failing if it contains any type errors.
     pub fn compile(
         ns: namespace::SharedNamespace<V>,
         src: &'i str,
     ) -> Result<Self, CompileError<'i>> {
-        use std::iter::empty;
-        let tree = frontend::parse(&*ns.borrow(), frontend::Rule::term, src)?;
-        let typer_result = typing::type_of(&*ns.borrow(), empty(), &tree);
+        Script::compile_with(ns, src, CompileOptions::strict())
+    }
+
+    /// Compile `src` against the declared types of the inputs the host will provide.
+    pub fn compile_with_globals(
+        ns: namespace::SharedNamespace<V>,
+        src: &'i str,
+        globals: collections::HashMap<String, typing::Type>,
+    ) -> Result<Self, CompileError<'i>> {
+        Script::compile_with(ns, src, CompileOptions::strict().with_globals(globals))
+    }
+
+    pub fn compile_with(
+        ns: namespace::SharedNamespace<V>,
+        src: &'i str,
+        options: CompileOptions,
+    ) -> Result<Self, CompileError<'i>> {
+        let tree = frontend::parse(&*ns.borrow(), src)?;
+        let typer_result = typing::type_of(&*ns.borrow(), options.globals, &tree);
+
+        if !options.lenient && !typer_result.errors.is_empty() {
+            return Err(CompileError::TypeErrors(typer_result.errors));
+        }
 
         let instructions =
             emitter::emit(&tree).map_err(|err| CompileError::NodeIdError(Stage::Emit, err))?;
 
         Ok(Script {
             ns: ns.clone(),
-            tree: tree,
-            instructions: instructions,
-            typer_result: typer_result,
+            tree,
+            instructions,
+            typer_result,
         })
     }
 
+    /// Check `inputs` against the input types of this script, returning every problem found.
+    ///
+    /// Each input the script uses must be present and satisfy its type. Inputs the script doesn't
+    /// use are ignored, so a host can pass the same inputs to every script. Amounts the script
+    /// combines must be in the same currency, even when the script doesn't say which.
+    pub fn check_inputs(
+        &self,
+        inputs: &collections::HashMap<String, V>,
+    ) -> Result<(), Vec<V::Error>> {
+        let mut errors = Vec::new();
+        let mut currencies = collections::HashMap::new();
+
+        let mut expected: Vec<_> = self.typer_result.inputs.iter().collect();
+        expected.sort_by_key(|&(name, _)| name);
+        for (name, ty) in expected {
+            match inputs.get(name) {
+                Some(value) => {
+                    if let Err(errs) = ty.satisfied_by_value_sharing(value, &mut currencies) {
+                        errors.extend(
+                            errs.into_iter()
+                                .map(|err| V::Error::from(format!("input `{}` {}", name, err))),
+                        );
+                    }
+                }
+                None => errors.push(V::Error::from(format!("input `{}` is required", name))),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
     pub fn eval(&self, inputs: collections::HashMap<String, V>) -> Result<V, V::Error> {
         use crate::interpreter::machine::Machine;
-        // use interpreter::interpreter::*;
+
+        self.check_inputs(&inputs).map_err(|errors| {
+            let messages: Vec<String> = errors.iter().map(|err| format!("{}", err)).collect();
+            V::Error::from(messages.join("\n"))
+        })?;
+
         let bindings: Vec<_> = inputs
             .into_iter()
             .filter_map(|(name, value)| self.tree.symbols.find(&name).map(|id| (id, value)))
@@ -94,12 +190,19 @@ impl<'i, V: Value> Script<'i, V> {
 
 #[cfg(test)]
 mod tests {
-    use super::Script;
+    use super::{CompileError, CompileOptions, Script};
     use crate::standalone::Value;
     use crate::test_helpers::*;
     use std::collections::HashMap;
     use std::iter::FromIterator;
 
+    /// Evaluate `script` with `value` as its only input.
+    fn eval_with(script: &Script<Value>, name: &str, value: Value) -> Result<Value, String> {
+        let mut inputs = HashMap::new();
+        inputs.insert(name.to_string(), value);
+        script.eval(inputs)
+    }
+
     #[test]
     fn test_function_call() {
         let ns = init_namespace().into_shared();
@@ -118,4 +221,514 @@ mod tests {
             result
         );
     }
+
+    #[test]
+    fn test_block_patterns() {
+        let ns = init_namespace().into_shared();
+        let script = Script::compile(
+            ns.clone(),
+            "offices = [[name = \"HQ\" address = [city = \"Oslo\"]] [name = \"Lab\" address = [city = \"Bergen\"]]]
+each: offices do: { [name = n address = [city = c]] => \"{n} in {c}\" }",
+        )
+        .unwrap();
+        assert_eq!(
+            script.eval(HashMap::new()).unwrap(),
+            Value::from_iter(vec![
+                Value::from("HQ in Oslo"),
+                Value::from("Lab in Bergen")
+            ])
+        );
+
+        let script = Script::compile(
+            ns,
+            "try: { each: [[1 2] [3]] do: { [a b] => calc: a plus: b } } or: []",
+        )
+        .unwrap();
+        assert_eq!(
+            script.eval(HashMap::new()).unwrap(),
+            Value::from_iter(Vec::<Value>::new())
+        );
+    }
+
+    #[test]
+    fn test_record_update() {
+        let ns = init_namespace().into_shared();
+        let script = Script::compile(
+            ns,
+            "order = [id = 1 status = \"new\"]
+paid = [order with status = \"paid\" total = 10]
+\"{order.status} {paid.id} {paid.status} {paid.total}\"",
+        )
+        .unwrap();
+        assert_eq!(
+            script.eval(HashMap::new()).unwrap(),
+            Value::from("new 1 paid 10")
+        );
+    }
+
+    #[test]
+    fn test_optional_values() {
+        use crate::typing::Type;
+
+        let ns = init_namespace().into_shared();
+        let mut globals = HashMap::new();
+        globals.insert("note".to_string(), Type::optional(Type::Str));
+        let script = Script::compile_with_globals(
+            ns,
+            "ifPresent: note then: { n => upperCase: n } else: withDefault: note or: \"-\"",
+            globals,
+        )
+        .unwrap();
+
+        let eval = |note: Value| eval_with(&script, "note", note);
+        assert_eq!(eval(Value::from("fragile")), Ok(Value::from("FRAGILE")));
+        assert_eq!(eval(Value::absent()), Ok(Value::from("-")));
+        assert!(eval(Value::from(1f64)).is_err());
+    }
+
+    #[test]
+    fn test_match_union_cases() {
+        let ns = init_namespace().into_shared();
+        let mut globals = HashMap::new();
+        globals.insert(
+            "status".to_string(),
+            "< pending | refunded(string) >".parse().unwrap(),
+        );
+        let script = Script::compile_with_globals(
+            ns,
+            "match: status on: [ pending = { \"not yet\" } refunded = { why => \"refunded: {why}\" } ]",
+            globals,
+        )
+        .unwrap();
+
+        let eval = |status: Value| eval_with(&script, "status", status);
+        assert_eq!(
+            eval(Value::variant("pending".into(), None)),
+            Ok(Value::from("not yet"))
+        );
+        assert_eq!(
+            eval(Value::variant(
+                "refunded".into(),
+                Some(Value::from("damaged"))
+            )),
+            Ok(Value::from("refunded: damaged"))
+        );
+        assert!(eval(Value::variant("paid".into(), None)).is_err());
+        assert!(eval(Value::variant("refunded".into(), None)).is_err());
+    }
+
+    #[test]
+    fn test_enum_inputs() {
+        use crate::typing::Type;
+
+        let ns = init_namespace().into_shared();
+        let mut globals = HashMap::new();
+        globals.insert(
+            "status".to_string(),
+            Type::Enum(vec!["pending".into(), "paid".into()]),
+        );
+        let script = Script::compile_with_globals(
+            ns.clone(),
+            "if: { equal: status to: \"paid\" } then: \"done\" else: \"waiting\"",
+            globals.clone(),
+        )
+        .unwrap();
+
+        let eval = |status: &str| eval_with(&script, "status", Value::from(status));
+        assert_eq!(eval("paid"), Ok(Value::from("done")));
+        assert_eq!(eval("pending"), Ok(Value::from("waiting")));
+        assert!(eval("payed").is_err());
+
+        assert!(Script::compile_with_globals(ns, "equal: status to: \"payed\"", globals).is_err());
+    }
+
+    #[test]
+    fn test_opaque_host_values() {
+        use crate::interpreter::OpaqueValue;
+        use crate::typing::Type;
+
+        let mut ns = init_namespace();
+        ns.define(|f| {
+            let customer = f.required_arg("customerName", Type::opaque("CustomerId"));
+            f.returns(Type::Str);
+            f.callback(move |args, _vm| {
+                let (_, handle) = args.demand(&customer)?.try_handle()?;
+                Ok(Value::from(format!("customer #{}", handle)))
+            });
+        })
+        .unwrap();
+        let ns = ns.into_shared();
+
+        let mut globals = HashMap::new();
+        globals.insert("customer".to_string(), Type::opaque("CustomerId"));
+        let script =
+            Script::compile_with_globals(ns.clone(), "customerName: customer", globals.clone())
+                .unwrap();
+        let eval = |customer: Value| eval_with(&script, "customer", customer);
+        assert_eq!(
+            eval(Value::opaque("CustomerId".into(), 7)),
+            Ok(Value::from("customer #7"))
+        );
+        assert_eq!(
+            eval(Value::opaque("OrderId".into(), 7)),
+            Err("input `customer` is of type OrderId, not CustomerId".to_string())
+        );
+        assert!(eval(Value::from("7")).is_err());
+
+        assert!(Script::compile(ns.clone(), "customerName: \"7\"").is_err());
+        assert!(Script::compile_with_globals(ns, "upperCase: customer", globals).is_err());
+    }
+
+    #[test]
+    fn test_string_interpolation() {
+        let ns = init_namespace().into_shared();
+        let script = Script::compile(ns, "\"{name}\\t{calc: 1 plus: 1.5} {true}\"").unwrap();
+        let mut inputs = HashMap::new();
+        inputs.insert("name".to_string(), Value::from("a \"b\""));
+        assert_eq!(
+            script.eval(inputs).unwrap(),
+            Value::from("a \"b\"\t2.5 true")
+        );
+
+        let ns = init_namespace().into_shared();
+        let script = Script::compile_with(ns, "\"{[1 2]}\"", CompileOptions::lenient()).unwrap();
+        assert_eq!(
+            script.eval(HashMap::new()),
+            Err::<Value, _>(
+                "only strings, numbers and true or false can be put in a string, but this is a list"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_time_functions() {
+        let eval = |src: &str| {
+            let ns = init_namespace().into_shared();
+            let script = Script::compile(ns, src).unwrap();
+            format!("{}", script.eval(HashMap::new()).unwrap())
+        };
+        assert_eq!(
+            eval("calcTime: 2024-01-31T18:45:00Z plus: P1D plus: PT6H"),
+            "2024-02-02T00:45:00Z"
+        );
+        assert_eq!(
+            eval("formatTime: 2024-02-02T00:45:00Z pattern: \"%Y/%m\""),
+            "\"2024/02\""
+        );
+        assert_eq!(
+            eval("startOfMonth: 2024-02-02T00:45:00Z"),
+            "2024-02-01T00:00:00Z"
+        );
+        assert_eq!(
+            eval("startOfDay: 2024-02-02T00:45:00Z"),
+            "2024-02-02T00:00:00Z"
+        );
+        assert_eq!(
+            eval("compareTime: 2024-02-02 after: 2024-02-01 atOrBefore: 2024-02-02"),
+            "true"
+        );
+        assert_eq!(
+            eval("durationFrom: 2024-02-02T00:45:00Z to: 2024-01-31"),
+            "P2DT45M"
+        );
+        assert_eq!(
+            eval("try: { calcTime: 1970-01-01 subtract: PT1S } or: 2000-01-01"),
+            "2000-01-01T00:00:00Z"
+        );
+    }
+
+    #[test]
+    fn test_money() {
+        use crate::namespace::{Namespace, NumberMode};
+
+        let eval = |src: &str, total: Value| {
+            let ns = init_namespace().into_shared();
+            let script = Script::compile(ns, src).unwrap();
+            eval_with(&script, "total", total).map(|result| format!("{}", result))
+        };
+        let usd = |amount: &str| Value::from((String::from("USD"), amount.parse().unwrap()));
+        assert_eq!(
+            eval(
+                "try: { calcMoney: total plus: 0.10 USD plus: 0.20 USD times: 3 dividedBy: 7 } \
+                 or: 0 USD",
+                usd("10.00")
+            ),
+            Ok("4.41 USD".into())
+        );
+        assert_eq!(
+            eval("compareMoney: total atLeast: 10 USD", usd("10.00")),
+            Ok("true".into())
+        );
+        assert_eq!(
+            eval("calcMoney: total plus: 1 USD", Value::from(1f64)),
+            Err("input `total` 1 is not an amount of money".into())
+        );
+
+        let euros = Value::from((String::from("EUR"), "5".parse().unwrap()));
+        let ns = init_namespace().into_shared();
+        let mut globals = HashMap::new();
+        globals.insert("total".to_string(), crate::typing::Type::money());
+        let script = Script::compile_with_globals(
+            ns,
+            "try: { checkCurrency: total like: 0 USD } or: 0 USD",
+            globals,
+        )
+        .unwrap();
+        let result = eval_with(&script, "total", euros).unwrap();
+        assert_eq!(format!("{}", result), "0 USD");
+
+        // decimal operands are used as they are, without going through a float
+        let ns = Namespace::<Value>::new_with_prelude_using(NumberMode::Decimal)
+            .unwrap()
+            .into_shared();
+        let src = "calcMoney: 1.00000000000000000000 USD times: 1.00000000000000000001";
+        let script = Script::compile(ns, src).unwrap();
+        assert_eq!(
+            format!("{}", script.eval(HashMap::new()).unwrap()),
+            "1.00000000000000000001 USD"
+        );
+    }
+
+    #[test]
+    fn test_amounts_in_mixed_currencies() {
+        let ns = init_namespace().into_shared();
+        let script = Script::compile(ns, "compareMoney: a atMost: calcMoney: b plus: c").unwrap();
+        let money = |currency: &str| Value::from((String::from(currency), "1".parse().unwrap()));
+
+        let mut inputs = HashMap::new();
+        inputs.insert("a".to_string(), money("USD"));
+        inputs.insert("b".to_string(), money("USD"));
+        inputs.insert("c".to_string(), money("EUR"));
+        assert_eq!(
+            script.check_inputs(&inputs),
+            Err(vec![
+                "input `c` the amount is in EUR, not USD like the amounts it's used with"
+                    .to_string()
+            ])
+        );
+        assert!(script.eval(inputs).is_err());
+
+        let mut inputs = HashMap::new();
+        inputs.insert("a".to_string(), money("EUR"));
+        inputs.insert("b".to_string(), money("EUR"));
+        inputs.insert("c".to_string(), money("EUR"));
+        assert_eq!(script.eval(inputs), Ok(Value::from(true)));
+    }
+
+    #[test]
+    fn test_decimal_numbers() {
+        use crate::namespace::{Namespace, NumberMode};
+
+        let eval = |numbers: NumberMode, src: &str, x: Value| {
+            let ns = Namespace::new_with_prelude_using(numbers)
+                .unwrap()
+                .into_shared();
+            let script = Script::compile(ns, src).unwrap();
+            let mut inputs = HashMap::new();
+            inputs.insert("x".to_string(), x);
+            format!("{}", script.eval(inputs).unwrap())
+        };
+        let src = "calc: 0.1 plus: 0.2 times: x";
+        assert_eq!(
+            eval(NumberMode::Float, src, Value::from(1f64)),
+            "0.30000000000000004"
+        );
+        assert_eq!(eval(NumberMode::Decimal, src, Value::from(1f64)), "0.3");
+        assert_eq!(
+            eval(
+                NumberMode::Decimal,
+                "try: { calc: x dividedBy: 3 } or: 0",
+                Value::from(1f64)
+            ),
+            "0.33333333333333333333"
+        );
+        assert_eq!(
+            eval(NumberMode::Decimal, "sum: [0.1 0.2 x]", Value::from(0.3)),
+            "0.6"
+        );
+        assert_eq!(
+            eval(
+                NumberMode::Decimal,
+                "compare: 0.3 atMost: calc: 0.1 plus: x",
+                Value::from(0.2)
+            ),
+            "true"
+        );
+        assert_eq!(
+            eval(
+                NumberMode::Decimal,
+                "countFrom: x to: 0.3 by: 0.1",
+                Value::from(0f64)
+            ),
+            "[ 0 0.1 0.2 0.3 ]"
+        );
+
+        // exponents too far out either way are rejected rather than expanded or wrapped around
+        for src in ["calc: 1e20000000000 plus: x", "calc: 1e-4294967297 plus: x"].iter() {
+            let ns = Namespace::<Value>::new_with_prelude_using(NumberMode::Decimal)
+                .unwrap()
+                .into_shared();
+            let error = Script::compile(ns, src).err().expect(src);
+            assert!(
+                format!("{}", error).contains("is not a valid number"),
+                "{}",
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn test_functions_defined_from_signatures() {
+        use crate::interpreter::Value as IValue;
+
+        let mut ns = init_namespace();
+        let keywords = ns
+            .define_from_str(
+                "repeat: string [times]?!: number :: string",
+                |keywords, args, _vm| {
+                    let mut out = String::from(args.demand(&keywords[0])?.try_string()?);
+                    for n in args.all(keywords[1]) {
+                        let n = n.try_number()?;
+                        if n < 0f64 {
+                            return Err("can't repeat a negative number of times".into());
+                        }
+                        out = out.repeat(n as usize);
+                    }
+                    Ok(Value::from(out))
+                },
+            )
+            .unwrap();
+        assert_eq!(keywords.len(), 2);
+        assert_eq!(
+            ns.define_from_str("repeat: number :: number", |_, _, _| Ok(Value::from(0f64))),
+            Err("function `repeat` already defined".to_string())
+        );
+
+        let ns = ns.into_shared();
+        assert!(Script::compile(ns.clone(), "repeat: \"ab\" times: 2").is_err());
+        let script =
+            Script::compile(ns, "try: { repeat: \"ab\" times: 2 times: 2 } or: \"\"").unwrap();
+        assert_eq!(
+            script.eval(HashMap::new()).unwrap().try_string(),
+            Ok("abababab")
+        );
+    }
+
+    #[test]
+    fn test_records_with_extra_fields() {
+        use crate::interpreter::Value as IValue;
+        use crate::typing::Type;
+
+        let mut ns = init_namespace();
+        ns.define_from_str(
+            "total: [ price=number quantity=number ] :: number",
+            |keywords, args, _vm| {
+                let item = args.demand(&keywords[0])?.try_record()?;
+                let field = |name| item.get(name).ok_or(format!("no {}", name));
+                Ok(Value::from(
+                    field("price")?.try_number()? * field("quantity")?.try_number()?,
+                ))
+            },
+        )
+        .unwrap();
+        let ns = ns.into_shared();
+
+        let script = Script::compile(
+            ns.clone(),
+            "total: [ price = 2 quantity = 3 note = \"gift\" ]",
+        )
+        .unwrap();
+        assert_eq!(script.eval(HashMap::new()), Ok(Value::from(6f64)));
+
+        let mut globals = HashMap::new();
+        globals.insert(
+            "item".to_string(),
+            "[ price=number quantity?=number ]".parse::<Type>().unwrap(),
+        );
+        assert!(Script::compile_with_globals(ns.clone(), "total: item", globals).is_err());
+        assert!(Script::compile(ns, "total: [ price = 2 ]").is_err());
+    }
+
+    #[test]
+    fn test_bindings() {
+        let ns = init_namespace().into_shared();
+        let script = Script::compile(
+            ns,
+            "subtotal = calc: price times: quantity
+total = calc: subtotal plus: shipping
+\"{subtotal} + {shipping} = {total}\"",
+        )
+        .unwrap();
+        let mut inputs = HashMap::new();
+        inputs.insert("price".to_string(), Value::from(2.5));
+        inputs.insert("quantity".to_string(), Value::from(4f64));
+        inputs.insert("shipping".to_string(), Value::from(3f64));
+        assert_eq!(script.eval(inputs).unwrap(), Value::from("10 + 3 = 13"));
+    }
+
+    #[test]
+    fn test_strict_and_lenient_compile() {
+        let ns = init_namespace().into_shared();
+        match Script::compile(ns.clone(), "calc: 1 dividedBy: 0") {
+            Err(CompileError::TypeErrors(errors)) => assert_eq!(errors.len(), 1),
+            Err(other) => panic!("unexpected compile error {:?}", other),
+            Ok(_) => panic!("strict compile accepted a script with type errors"),
+        }
+
+        let script =
+            Script::compile_with(ns, "calc: 1 dividedBy: 0", CompileOptions::lenient()).unwrap();
+        assert_eq!(script.typer_result.errors.len(), 1);
+        assert_eq!(
+            script.eval(HashMap::new()),
+            Err::<Value, _>("division by zero".to_string())
+        );
+    }
+
+    #[test]
+    fn test_declared_globals() {
+        use crate::typing::Type;
+
+        let ns = init_namespace().into_shared();
+        let mut globals = HashMap::new();
+        globals.insert("name".to_string(), Type::Str);
+        match Script::compile_with_globals(ns.clone(), "calc: name plus: 1", globals.clone()) {
+            Err(CompileError::TypeErrors(_)) => {}
+            Err(other) => panic!("unexpected compile error {:?}", other),
+            Ok(_) => panic!("script used a string input as a number"),
+        }
+
+        let script = Script::compile_with_globals(ns, "upperCase: name", globals).unwrap();
+        assert_eq!(script.typer_result.inputs.get("name"), Some(&Type::Str));
+    }
+
+    #[test]
+    fn test_inputs_checked_before_eval() {
+        let ns = init_namespace().into_shared();
+        let script = Script::compile(ns, "calc: x plus: y.z").unwrap();
+
+        let mut inputs = HashMap::new();
+        inputs.insert("x".to_string(), Value::from("one"));
+        inputs.insert("w".to_string(), Value::from(1f64));
+        assert_eq!(
+            script.check_inputs(&inputs),
+            Err(vec![
+                "input `x` \"one\" is not a number".to_string(),
+                "input `y` is required".to_string(),
+            ])
+        );
+        assert_eq!(
+            script.eval(inputs),
+            Err::<Value, _>("input `x` \"one\" is not a number\ninput `y` is required".to_string())
+        );
+
+        let mut inputs = HashMap::new();
+        inputs.insert("x".to_string(), Value::from(1f64));
+        inputs.insert(
+            "y".to_string(),
+            Value::from_iter(vec![("z".to_string(), Value::from(2f64))]),
+        );
+        inputs.insert("w".to_string(), Value::from("unused"));
+        assert_eq!(script.eval(inputs), Ok(Value::from(3f64)));
+    }
 }
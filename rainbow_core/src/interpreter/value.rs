@@ -1,6 +1,8 @@
+use crate::decimal::Decimal;
 use crate::with_error::WithError;
 use std::fmt::Debug;
 use std::iter::FromIterator;
+use std::time::Duration;
 
 use super::{Block, Machine};
 
@@ -21,6 +23,9 @@ pub trait Value:
     + From<bool>
     + From<String>
     + From<u64>
+    + From<Duration>
+    + From<(String, Decimal)>
+    + From<Decimal>
     + From<f64>
     + From<Vec<Self>>
     + FromIterator<Self>
@@ -33,10 +38,36 @@ pub trait Value:
     fn try_bool(&self) -> Result<bool, Self::Error>;
     fn try_string(&self) -> Result<&str, Self::Error>;
     fn try_number(&self) -> Result<f64, Self::Error>;
+    /// A number as an exact decimal. Floats are converted to the decimal they are displayed as.
+    fn try_decimal(&self) -> Result<Decimal, Self::Error> {
+        let n = self.try_number()?;
+        Decimal::from_f64(n)
+            .ok_or_else(|| Self::Error::from(format!("{} is not a finite number", n)))
+    }
     fn try_time(&self) -> Result<u64, Self::Error>;
+    fn try_duration(&self) -> Result<Duration, Self::Error>;
+    /// The currency code and amount of some money.
+    fn try_money(&self) -> Result<(&str, &Decimal), Self::Error>;
     fn try_list(&self) -> Result<Self::List, Self::Error>;
     fn try_record(&self) -> Result<Self::Record, Self::Error>;
     fn try_block(&self) -> Result<&Block, Self::Error>;
+    /// Is this the value of something that might be absent, when it is? Hosts without optional
+    /// values can leave this out.
+    fn is_absent(&self) -> bool {
+        false
+    }
+    /// The tag of a value that is one of the cases of a union, with the payload of that case if it
+    /// has one. Hosts without unions can leave this out.
+    fn try_variant(&self) -> Result<(&str, Option<Self>), Self::Error> {
+        Err(Self::Error::from(format!(
+            "{:?} is not one of the cases of a union",
+            self
+        )))
+    }
+    /// The name of the opaque type of a host value. Hosts without opaque types can leave this out.
+    fn try_opaque(&self) -> Result<&str, Self::Error> {
+        Err(Self::Error::from(format!("{:?} is not a host value", self)))
+    }
     fn callable(&self) -> bool {
         self.try_block().is_ok()
     }
@@ -47,9 +78,23 @@ pub trait Value:
 }
 
 /// List operation trait.
+/// A `Value` that can carry host handles through scripts, as the values of opaque types.
+pub trait OpaqueValue: Value {
+    /// Whatever the host keeps inside its opaque values, like a database id or a connection.
+    type Handle: Clone + Debug + PartialEq;
+
+    /// A host value with the name of its opaque type, which scripts can pass around but not inspect.
+    fn opaque(name: String, handle: Self::Handle) -> Self;
+    fn try_handle(&self) -> Result<(&str, &Self::Handle), Self::Error>;
+}
+
 pub trait List<V: Value>: IntoIterator<Item = V> + Debug {
     fn len(&self) -> usize;
     fn at(&self, idx: usize) -> Option<V>;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 /// Operations permissible on records
@@ -1,9 +1,5 @@
 extern crate id_tree;
 // extern crate parity_wasm;
-#[cfg_attr(test, macro_use)]
-extern crate pest;
-#[macro_use]
-extern crate pest_derive;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
@@ -12,6 +8,8 @@ extern crate serde_derive;
 mod macros;
 mod apply;
 mod arena;
+mod decimal;
+pub mod diagnostics;
 pub mod frontend;
 mod function_builder;
 pub mod interpreter;
@@ -19,6 +17,8 @@ mod namespace;
 mod primitive;
 mod scope;
 pub mod signature;
+mod suggest;
+mod time;
 mod typing;
 mod with_error;
 
@@ -29,7 +29,8 @@ mod prelude;
 pub mod standalone;
 
 pub use crate::apply::Apply;
-pub use crate::namespace::{INamespace, Namespace, SharedNamespace};
+pub use crate::decimal::Decimal;
+pub use crate::namespace::{INamespace, Namespace, NumberMode, SharedNamespace};
 pub use crate::primitive::Prim;
 pub use crate::scope::Scope;
 pub use crate::with_error::WithError;
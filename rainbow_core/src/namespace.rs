@@ -9,21 +9,39 @@ use crate::arena::*;
 use crate::function_builder::FunctionBuilder;
 use crate::interpreter::{Machine, Value};
 use crate::signature::Signature;
+use crate::with_error::WithError;
 
 pub type SharedNamespace<V> = Rc<RefCell<Namespace<V>>>;
 
+/// The implementation of a function, given its arguments and the machine calling it.
+pub type Callback<V> = Box<dyn Fn(Apply<V>, &mut Machine<V>) -> Result<V, <V as WithError>::Error>>;
+
 pub trait INamespace {
     fn new_empty() -> Self;
     fn get_signature(&self, name: &str) -> Option<&Signature>;
     fn symbols(&self) -> &Arena<String>;
+    fn number_mode(&self) -> NumberMode;
+}
+
+/// How numbers are represented by scripts compiled against a namespace.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NumberMode {
+    /// Numbers are `f64`s, so `calc: 0.1 plus: 0.2` is `0.30000000000000004`.
+    #[default]
+    Float,
+    /// Numbers are exact `Decimal`s, and the prelude does its arithmetic without rounding, except
+    /// for quotients that don't end.
+    Decimal,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct Namespace<V: Value> {
     signatures: HashMap<ArenaId, Signature>,
     symbols: Arena<String>,
+    #[serde(default)]
+    numbers: NumberMode,
     #[serde(skip_serializing, skip_deserializing, default = "HashMap::new")]
-    callbacks: HashMap<ArenaId, Box<Fn(Apply<V>, &mut Machine<V>) -> Result<V, V::Error>>>,
+    callbacks: HashMap<ArenaId, Callback<V>>,
 }
 
 impl<V: Value> Default for Namespace<V> {
@@ -50,6 +68,7 @@ impl<V: Value> INamespace for Namespace<V> {
             signatures: HashMap::new(),
             callbacks: HashMap::new(),
             symbols: Arena::with_capacity(256),
+            numbers: NumberMode::Float,
         }
     }
 
@@ -62,16 +81,31 @@ impl<V: Value> INamespace for Namespace<V> {
     fn symbols(&self) -> &Arena<String> {
         &self.symbols
     }
+
+    fn number_mode(&self) -> NumberMode {
+        self.numbers
+    }
 }
 
 impl<V: Value> Namespace<V> {
     pub fn new_with_prelude() -> Result<Self, String> {
+        Self::new_with_prelude_using(NumberMode::Float)
+    }
+
+    /// A namespace with the prelude installed, where numbers are represented as `numbers`.
+    pub fn new_with_prelude_using(numbers: NumberMode) -> Result<Self, String> {
         use crate::prelude;
-        let mut ns = Self::new_empty();
+        let mut ns = Self::new_empty().using(numbers);
         prelude::install(&mut ns)?;
         Ok(ns)
     }
 
+    /// This namespace with numbers represented as `numbers`. Functions defined before this was
+    /// changed are not redefined, so it should be set before any are.
+    pub fn using(self, numbers: NumberMode) -> Self {
+        Namespace { numbers, ..self }
+    }
+
     pub fn into_shared(self) -> SharedNamespace<V> {
         Rc::new(RefCell::new(self))
     }
@@ -81,10 +115,7 @@ impl<V: Value> Namespace<V> {
     }
 
     #[inline]
-    pub fn get_callback(
-        &self,
-        id: &ArenaId,
-    ) -> Option<&Box<Fn(Apply<V>, &mut Machine<V>) -> Result<V, V::Error>>> {
+    pub fn get_callback(&self, id: &ArenaId) -> Option<&Callback<V>> {
         self.callbacks.get(id)
     }
 
@@ -96,11 +127,7 @@ impl<V: Value> Namespace<V> {
         self.symbols.resolve(id)
     }
 
-    pub fn insert(
-        &mut self,
-        signature: Signature,
-        callback: Box<Fn(Apply<V>, &mut Machine<V>) -> Result<V, V::Error>>,
-    ) -> Result<(), String> {
+    pub fn insert(&mut self, signature: Signature, callback: Callback<V>) -> Result<(), String> {
         let name = signature.name();
         if self.signatures.contains_key(&name) {
             return Err(format!(
@@ -113,7 +140,30 @@ impl<V: Value> Namespace<V> {
         Ok(())
     }
 
-    pub fn define<F: Fn(&mut FunctionBuilder<V>) -> ()>(&mut self, f: F) -> Result<(), String> {
+    /// Define a function from its signature, written like `Signature` displays one.
+    ///
+    /// Returns the ids of the keywords in the order they are written, which are also passed to
+    /// `callback` so that it can look up its arguments.
+    pub fn define_from_str<F>(
+        &mut self,
+        signature: &str,
+        callback: F,
+    ) -> Result<Vec<ArenaId>, String>
+    where
+        F: 'static + Fn(&[ArenaId], Apply<V>, &mut Machine<V>) -> Result<V, V::Error>,
+    {
+        let parsed: Signature<String> = signature.parse()?;
+        let signature = parsed.map_names(|name| self.symbols.intern(name.as_str()));
+        let keywords: Vec<ArenaId> = signature.args().map(|arg| arg.name).collect();
+        let ids = keywords.clone();
+        self.insert(
+            signature,
+            Box::new(move |args, vm| callback(&ids, args, vm)),
+        )?;
+        Ok(keywords)
+    }
+
+    pub fn define<F: Fn(&mut FunctionBuilder<V>)>(&mut self, f: F) -> Result<(), String> {
         let (signature, callback) = {
             let mut builder: FunctionBuilder<V> = FunctionBuilder::new(&mut self.symbols);
             f(&mut builder);
@@ -1,10 +1,21 @@
-use crate::interpreter::Value;
-use crate::namespace::Namespace;
+use crate::decimal::Decimal;
+use crate::interpreter::{Record, Value};
+use crate::namespace::{INamespace, Namespace, NumberMode};
+use crate::time;
 use crate::typing::Type;
+use std::cmp::Ordering::{Equal, Greater, Less};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// The decimal places a quotient is rounded to when numbers are decimals and it doesn't end.
+const QUOTIENT_SCALE: u32 = 20;
 
 pub fn install<V: Value>(ns: &mut Namespace<V>) -> Result<(), String> {
+    let exact = ns.number_mode() == NumberMode::Decimal;
+
     ns.define(|f| {
         let msg = f.required_arg("crash", Type::Str);
+        f.is_partial();
         f.returns(Type::Any);
         f.callback(move |args, _vm| {
             let v = args.demand(&msg)?;
@@ -34,9 +45,7 @@ pub fn install<V: Value>(ns: &mut Namespace<V>) -> Result<(), String> {
         f.callback(move |args, vm| {
             let mut yes_no: bool = args.demand(&if_)?.try_bool()?;
             for &(keyword, ref val) in args.iter().skip(1) {
-                if yes_no && keyword == and {
-                    yes_no = val.try_call(vm, vec![])?.try_bool()?;
-                } else if !yes_no && keyword == or {
+                if (yes_no && keyword == and) || (!yes_no && keyword == or) {
                     yes_no = val.try_call(vm, vec![])?.try_bool()?;
                 } else if keyword == then || keyword == else_ {
                     break;
@@ -49,26 +58,36 @@ pub fn install<V: Value>(ns: &mut Namespace<V>) -> Result<(), String> {
         });
     })?;
 
+    ns.define(|f| {
+        let equal = f.required_arg("equal", Type::var("A"));
+        let to = f.required_arg("to", Type::var("A"));
+        f.returns(Type::Bool);
+        f.callback(move |args, _vm| Ok((args.demand(&equal)? == args.demand(&to)?).into()));
+    })?;
+
     ns.define(|f| {
         let cmp = f.required_arg("compare", Type::Num);
         let gt = f.optional_arg("biggerThan", Type::Num);
         let gte = f.optional_arg("atLeast", Type::Num);
         let lt = f.optional_arg("smallerThan", Type::Num);
         let lte = f.optional_arg("atMost", Type::Num);
-        f.is_total();
         f.returns(Type::Bool);
         f.callback(move |args, _vm| {
-            let it = args.demand(&cmp)?.try_number()?;
+            let it = args.demand(&cmp)?;
             for &(keyword, ref val) in args.iter().skip(1) {
-                let other = val.try_number()?;
+                let order = if exact {
+                    Some(it.try_decimal()?.cmp(&val.try_decimal()?))
+                } else {
+                    it.try_number()?.partial_cmp(&val.try_number()?)
+                };
                 let pass = if keyword == gt {
-                    it > other
+                    order == Some(Greater)
                 } else if keyword == gte {
-                    it >= other
+                    matches!(order, Some(Greater | Equal))
                 } else if keyword == lt {
-                    it < other
+                    order == Some(Less)
                 } else if keyword == lte {
-                    it <= other
+                    matches!(order, Some(Less | Equal))
                 } else {
                     true
                 };
@@ -107,11 +126,64 @@ pub fn install<V: Value>(ns: &mut Namespace<V>) -> Result<(), String> {
         });
     })?;
 
+    ns.define(|f| {
+        let with_default = f.required_arg("withDefault", Type::optional(Type::var("A")));
+        let or = f.required_arg("or", Type::quoted(Type::var("A")));
+        f.returns(Type::var("A"));
+        f.callback(move |args, vm| {
+            let value = args.demand(&with_default)?;
+            if value.is_absent() {
+                args.demand(&or)?.try_call(vm, vec![])
+            } else {
+                Ok(value.clone())
+            }
+        });
+    })?;
+
+    ns.define(|f| {
+        let if_present = f.required_arg("ifPresent", Type::optional(Type::var("A")));
+        let then_type = Type::block_from_to(vec![Type::var("A")], Type::var("B"));
+        let then = f.required_arg("then", then_type);
+        let else_ = f.required_arg("else", Type::quoted(Type::var("B")));
+        f.returns(Type::var("B"));
+        f.callback(move |args, vm| {
+            let value = args.demand(&if_present)?;
+            if value.is_absent() {
+                args.demand(&else_)?.try_call(vm, vec![])
+            } else {
+                args.demand(&then)?.try_call(vm, vec![value.clone()])
+            }
+        });
+    })?;
+
+    ns.define(|f| {
+        let match_ = f.required_arg("match", Type::var("A"));
+        let on = f.required_arg("on", Type::Record(true, HashMap::new()));
+        f.returns(Type::var("B"));
+        f.matches_cases();
+        f.callback(move |args, vm| {
+            let (tag, payload) = args.demand(&match_)?.try_variant()?;
+            let case = args
+                .demand(&on)?
+                .try_record()?
+                .at(tag)
+                .ok_or_else(|| V::Error::from(format!("there is no case for `{}`", tag)))?;
+            case.try_call(vm, payload.into_iter().collect())
+        });
+    })?;
+
     ns.define(|f| {
         let sum = f.required_arg("sum", Type::list_of(Type::Num));
         f.returns(Type::Num);
         f.callback(move |args, _vm| {
             let list = args.demand(&sum)?.try_list()?;
+            if exact {
+                let mut sum = Decimal::zero();
+                for item in list {
+                    sum = sum.add(&item.try_decimal()?);
+                }
+                return Ok(sum.into());
+            }
             let mut sum = 0_f64;
             for item in list {
                 sum += item.try_number()?;
@@ -126,6 +198,32 @@ pub fn install<V: Value>(ns: &mut Namespace<V>) -> Result<(), String> {
         let by = f.optional_arg("by", Type::Num);
         f.returns(Type::list_of(Type::Num));
         f.callback(move |args, _vm| {
+            if exact {
+                let start = args.demand(&count_f)?.try_decimal()?;
+                let mut step = args
+                    .demand(&by)
+                    .and_then(|v| v.try_decimal())
+                    .unwrap_or_else(|_| Decimal::one());
+                let end = args.demand(&to)?.try_decimal()?;
+
+                if step.is_zero() {
+                    step = Decimal::one();
+                }
+
+                let negative = step < Decimal::zero();
+                if (start > end && !negative) || (start < end && negative) {
+                    step = step.negated();
+                }
+
+                let mut out: Vec<V> = Vec::new();
+                let mut here = start;
+                while here <= end {
+                    out.push(here.clone().into());
+                    here = here.add(&step);
+                }
+                return Ok(out.into());
+            }
+
             let start = args.demand(&count_f)?.try_number()?;
             let mut step = args
                 .demand(&by)
@@ -161,12 +259,40 @@ pub fn install<V: Value>(ns: &mut Namespace<V>) -> Result<(), String> {
         let div = f.variadic_arg("dividedBy", Type::Num);
         f.returns(Type::Num);
         f.callback(move |args, _vm| {
+            if exact {
+                let init = args.demand(&calc)?.try_decimal();
+                return args
+                    .iter()
+                    .skip(1)
+                    .fold(init, |result, &(keyword, ref val)| {
+                        let r = result?;
+                        let n = val.try_decimal()?;
+                        Ok(if keyword == add {
+                            r.add(&n)
+                        } else if keyword == sub {
+                            r.sub(&n)
+                        } else if keyword == mul {
+                            r.mul(&n)
+                        } else if keyword == div {
+                            r.div(&n, QUOTIENT_SCALE)
+                                .ok_or_else(|| V::Error::from("division by zero"))?
+                                .normalized()
+                        } else {
+                            r
+                        })
+                    })
+                    .map(V::from);
+            }
+
             let init = args.demand(&calc)?.try_number();
             args.iter()
                 .skip(1)
                 .fold(init, |result, &(keyword, ref val)| {
                     let r = result?;
                     let n = val.try_number()?;
+                    if keyword == div && n == 0_f64 {
+                        return Err(V::Error::from("division by zero"));
+                    }
                     Ok(if keyword == add {
                         r + n
                     } else if keyword == sub {
@@ -181,7 +307,7 @@ pub fn install<V: Value>(ns: &mut Namespace<V>) -> Result<(), String> {
                 })
                 .map(V::from)
         });
-        f.is_partial(); // division by zero will fail
+        f.is_partial_when(div); // division by zero will fail
     })?;
 
     // with: 12 do: { x => calc: 1 add: x }
@@ -205,6 +331,205 @@ pub fn install<V: Value>(ns: &mut Namespace<V>) -> Result<(), String> {
         });
     })?;
 
+    ns.define(|f| {
+        let cmp = f.required_arg("compareTime", Type::Time);
+        let before = f.optional_arg("before", Type::Time);
+        let after = f.optional_arg("after", Type::Time);
+        let at_or_before = f.optional_arg("atOrBefore", Type::Time);
+        let at_or_after = f.optional_arg("atOrAfter", Type::Time);
+        f.returns(Type::Bool);
+        f.callback(move |args, _vm| {
+            let it = args.demand(&cmp)?.try_time()?;
+            for &(keyword, ref val) in args.iter().skip(1) {
+                let other = val.try_time()?;
+                let pass = if keyword == before {
+                    it < other
+                } else if keyword == after {
+                    it > other
+                } else if keyword == at_or_before {
+                    it <= other
+                } else if keyword == at_or_after {
+                    it >= other
+                } else {
+                    true
+                };
+                if !pass {
+                    return Ok(pass.into());
+                }
+            }
+            Ok(true.into())
+        });
+    })?;
+
+    // calcTime: order.placedAt plus: P30D
+    ns.define(|f| {
+        let calc = f.required_arg("calcTime", Type::Time);
+        let add = f.variadic_arg("plus", Type::Duration);
+        let sub = f.variadic_arg("subtract", Type::Duration);
+        f.returns(Type::Time);
+        f.callback(move |args, _vm| {
+            let mut t = args.demand(&calc)?.try_time()?;
+            for &(keyword, ref val) in args.iter().skip(1) {
+                let ms = time::duration_millis(&val.try_duration()?);
+                if keyword == add {
+                    t = t.saturating_add(ms);
+                } else if keyword == sub {
+                    t = t
+                        .checked_sub(ms)
+                        .ok_or_else(|| V::Error::from("the time would be before 1970"))?;
+                }
+            }
+            Ok(V::from(t))
+        });
+        f.is_partial_when(sub); // times can't go before 1970
+    })?;
+
+    ns.define(|f| {
+        let from = f.required_arg("durationFrom", Type::Time);
+        let to = f.required_arg("to", Type::Time);
+        f.returns(Type::Duration);
+        f.callback(move |args, _vm| {
+            let from = args.demand(&from)?.try_time()?;
+            let to = args.demand(&to)?.try_time()?;
+            let ms = to.abs_diff(from);
+            Ok(V::from(Duration::from_millis(ms)))
+        });
+    })?;
+
+    ns.define(|f| {
+        let day = f.required_arg("startOfDay", Type::Time);
+        f.returns(Type::Time);
+        f.callback(move |args, _vm| {
+            let t = args.demand(&day)?.try_time()?;
+            Ok(V::from(time::start_of_day(t)))
+        });
+    })?;
+
+    ns.define(|f| {
+        let month = f.required_arg("startOfMonth", Type::Time);
+        f.returns(Type::Time);
+        f.callback(move |args, _vm| {
+            let t = args.demand(&month)?.try_time()?;
+            Ok(V::from(time::start_of_month(t)))
+        });
+    })?;
+
+    // formatTime: invoice.due pattern: "%d/%m/%Y"
+    ns.define(|f| {
+        let format = f.required_arg("formatTime", Type::Time);
+        let pattern = f.optional_arg("pattern", Type::Str);
+        f.returns(Type::Str);
+        f.callback(move |args, _vm| {
+            let t = args.demand(&format)?.try_time()?;
+            Ok(V::from(match args.get(&pattern) {
+                Some(pattern) => time::format_time_with(t, pattern.try_string()?),
+                None => time::format_time(t),
+            }))
+        });
+    })?;
+
+    // calcMoney: order.subtotal plus: order.shipping times: 1.2
+    ns.define(|f| {
+        let money = Type::Money(Box::new(Type::var("C")));
+        let calc = f.required_arg("calcMoney", money.clone());
+        let add = f.variadic_arg("plus", money.clone());
+        let sub = f.variadic_arg("subtract", money.clone());
+        let mul = f.variadic_arg("times", Type::Num);
+        let div = f.variadic_arg("dividedBy", Type::Num);
+        f.returns(money);
+        f.callback(move |args, _vm| {
+            let (currency, amount) = args.demand(&calc)?.try_money()?;
+            let mut result = amount.clone();
+            for &(keyword, ref val) in args.iter().skip(1) {
+                if keyword == add || keyword == sub {
+                    let (other_currency, other) = val.try_money()?;
+                    if other_currency != currency {
+                        return Err(V::Error::from(format!(
+                            "can't combine amounts in {} and {}",
+                            currency, other_currency
+                        )));
+                    }
+                    result = if keyword == add {
+                        result.add(other)
+                    } else {
+                        result.sub(other)
+                    };
+                } else {
+                    // products and quotients are rounded to the same number of decimal places
+                    let n = val.try_decimal()?;
+                    let scale = result.scale();
+                    result = if keyword == mul {
+                        result.mul(&n).round(scale)
+                    } else {
+                        result
+                            .div(&n, scale)
+                            .ok_or_else(|| V::Error::from("division by zero"))?
+                    };
+                }
+            }
+            Ok(V::from((String::from(currency), result)))
+        });
+        f.is_partial_when(div); // division by zero will fail
+    })?;
+
+    ns.define(|f| {
+        let money = Type::Money(Box::new(Type::var("C")));
+        let cmp = f.required_arg("compareMoney", money.clone());
+        let gt = f.optional_arg("biggerThan", money.clone());
+        let gte = f.optional_arg("atLeast", money.clone());
+        let lt = f.optional_arg("smallerThan", money.clone());
+        let lte = f.optional_arg("atMost", money);
+        f.returns(Type::Bool);
+        f.callback(move |args, _vm| {
+            let (currency, it) = args.demand(&cmp)?.try_money()?;
+            for &(keyword, ref val) in args.iter().skip(1) {
+                let (other_currency, other) = val.try_money()?;
+                if other_currency != currency {
+                    return Err(V::Error::from(format!(
+                        "can't compare amounts in {} and {}",
+                        currency, other_currency
+                    )));
+                }
+                let pass = if keyword == gt {
+                    it > other
+                } else if keyword == gte {
+                    it >= other
+                } else if keyword == lt {
+                    it < other
+                } else if keyword == lte {
+                    it <= other
+                } else {
+                    true
+                };
+                if !pass {
+                    return Ok(pass.into());
+                }
+            }
+            Ok(true.into())
+        });
+    })?;
+
+    // checkCurrency: invoice.total like: 0 USD
+    ns.define(|f| {
+        let check = f.required_arg("checkCurrency", Type::money());
+        let like = f.required_arg("like", Type::Money(Box::new(Type::var("C"))));
+        f.is_partial();
+        f.returns(Type::Money(Box::new(Type::var("C"))));
+        f.callback(move |args, _vm| {
+            let amount = args.demand(&check)?;
+            let (currency, _) = amount.try_money()?;
+            let (expected, _) = args.demand(&like)?.try_money()?;
+            if currency == expected {
+                Ok(amount.clone())
+            } else {
+                Err(V::Error::from(format!(
+                    "the amount is in {}, not {}",
+                    currency, expected
+                )))
+            }
+        });
+    })?;
+
     ns.define(|f| {
         let stringify = f.required_arg("stringify", Type::var("Any"));
         f.returns(Type::Str);
@@ -1,23 +1,32 @@
+use crate::decimal::Decimal;
+use crate::time;
 use crate::typing::Type;
 use std::fmt::{Display, Error as FmtError, Formatter};
+use std::time::Duration;
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Prim {
     Boolean(bool),
     Number(f64),
+    /// A number in a namespace using `NumberMode::Decimal`.
+    Decimal(Decimal),
     String(String),
+    /// Milliseconds since 1970-01-01T00:00:00Z.
     Time(u64),
-    Money(String, f64),
+    Duration(Duration),
+    /// An amount in a currency, named by its ISO 4217 code like `USD`.
+    Money(String, Decimal),
 }
 
 impl Prim {
     pub fn type_of(&self) -> Type {
         match *self {
-            Prim::Number(_) => Type::Num,
+            Prim::Number(_) | Prim::Decimal(_) => Type::Num,
             Prim::String(_) => Type::Str,
             Prim::Boolean(_) => Type::Bool,
             Prim::Time(_) => Type::Time,
-            Prim::Money(_, _) => Type::Money,
+            Prim::Duration(_) => Type::Duration,
+            Prim::Money(ref currency, _) => Type::money_in(currency),
         }
     }
 }
@@ -40,7 +49,19 @@ impl From<i32> for Prim {
     }
 }
 
-impl<'a> From<&'a str> for Prim {
+impl From<Decimal> for Prim {
+    fn from(d: Decimal) -> Prim {
+        Prim::Decimal(d)
+    }
+}
+
+impl From<Duration> for Prim {
+    fn from(d: Duration) -> Prim {
+        Prim::Duration(d)
+    }
+}
+
+impl From<&str> for Prim {
     fn from(s: &str) -> Prim {
         Prim::String(String::from(s))
     }
@@ -58,9 +79,29 @@ impl Display for Prim {
         match *self {
             Boolean(v) => write!(f, "{}", v),
             Number(v) => write!(f, "{}", v),
-            String(ref v) => write!(f, "{:?}", v),
-            Time(v) => write!(f, "{:?}", v),
-            Money(ref currency, amount) => write!(f, "{}{}", amount, currency),
+            Decimal(ref v) => write!(f, "{}", v),
+            String(ref v) => write!(f, "\"{}\"", escape(v)),
+            Time(v) => f.write_str(&time::format_time(v)),
+            Duration(ref d) => f.write_str(&time::format_duration(d)),
+            Money(ref currency, ref amount) => write!(f, "{} {}", amount, currency),
+        }
+    }
+}
+
+/// Escape `text` so that it can be written between the quotes of a string literal.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '{' => escaped.push_str("\\{"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
         }
     }
+    escaped
 }
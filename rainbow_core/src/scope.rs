@@ -73,17 +73,14 @@ where
     K: Hash + Eq + Clone + Debug,
 {
     fn new(locals: Locals<V, K>, parent: Option<FramePtr<V, K>>) -> FramePtr<V, K> {
-        Rc::new(RefCell::new(Frame {
-            locals: locals,
-            parent: parent,
-        }))
+        Rc::new(RefCell::new(Frame { locals, parent }))
     }
 
     fn get(&self, key: &K) -> Option<Rc<V>> {
         match (self.locals.get(key), &self.parent) {
             (Some(v), _) => Some(v.clone()),
-            (None, &Some(ref p)) => p.borrow().get(key),
-            (None, &None) => None,
+            (None, Some(p)) => p.borrow().get(key),
+            (None, None) => None,
         }
     }
 
@@ -95,9 +92,9 @@ where
     }
 
     fn flatten(&self) -> HashMap<K, Rc<V>> {
-        match &self.parent {
-            &None => self.locals.clone(),
-            &Some(ref p) => {
+        match self.parent {
+            None => self.locals.clone(),
+            Some(ref p) => {
                 let mut flattened = p.borrow().flatten();
                 for (k, v) in &self.locals {
                     flattened.insert(k.clone(), v.clone());
@@ -111,15 +108,15 @@ where
     where
         F: Fn(&V) -> V2,
     {
-        let parent = match self.parent {
-            None => None,
-            Some(ref parent_frame_ptr) => Some((*parent_frame_ptr).borrow().map_clone(f).clone()),
-        };
+        let parent = self
+            .parent
+            .as_ref()
+            .map(|parent_frame_ptr| (*parent_frame_ptr).borrow().map_clone(f).clone());
         let locals: Locals<V2, K> = self
             .locals
             .iter()
-            .map(|(ref k, ref v)| {
-                ((*k).clone(), Rc::new(/* */ f(v.as_ref()) /* */))
+            .map(|(k, ref v)| {
+                (k.clone(), Rc::new(/* */ f(v.as_ref()) /* */))
             })
             .collect();
         Frame::new(locals, parent)
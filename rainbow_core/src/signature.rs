@@ -1,16 +1,28 @@
 use crate::arena::ArenaId;
 use std::fmt::{Display, Error as FmtError, Formatter};
 use std::hash::Hash;
+use std::str::FromStr;
 
 use crate::typing::Type;
 
 /// Signature defines the types of inputs/outputs to a function.
+///
+/// It is written as each keyword followed by the type of its argument, then `::` and the return
+/// type, like `each: [ In... ] do: { In => Out } :: [ Out... ]`. A variadic keyword is written in
+/// brackets like `[plus]:`, and an optional one is followed by `?` like `by?:`. A `!` after the
+/// first keyword marks a partial function, and after any other marks an argument that makes a
+/// call partial, like `[dividedBy]?!:`.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Signature<Id: Clone + Hash + Eq = ArenaId> {
     args: Vec<Argument<Id>>,
     return_type: Type,
-    // total functions guarantee that they will return a value
+    // total functions guarantee that they will return a value; functions are total unless marked
+    // partial
     total: bool,
+    /// The function calls the block in its second argument, a record, named by the case of the
+    /// union in its first, like `match:`. Calls are checked to cover every case.
+    #[serde(default)]
+    matches_cases: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -19,6 +31,9 @@ pub struct Argument<Id: Clone + Hash + Eq = ArenaId> {
     pub ty: Type,
     pub variadic: bool,
     pub required: bool,
+    /// Supplying this argument makes a call partial, even if the function is otherwise total.
+    #[serde(default)]
+    pub partial: bool,
 }
 
 impl<Id: Clone + Hash + Eq> Signature<Id> {
@@ -26,7 +41,8 @@ impl<Id: Clone + Hash + Eq> Signature<Id> {
         Signature {
             args: Vec::with_capacity(capacity),
             return_type: Type::Never,
-            total: false,
+            total: true,
+            matches_cases: false,
         }
     }
 
@@ -38,6 +54,16 @@ impl<Id: Clone + Hash + Eq> Signature<Id> {
         self.total = total;
     }
 
+    pub fn set_matches_cases(&mut self, matches_cases: bool) {
+        self.matches_cases = matches_cases;
+    }
+
+    pub fn set_partial_arg(&mut self, name: Id) {
+        for arg in self.args.iter_mut().filter(|arg| arg.name == name) {
+            arg.partial = true;
+        }
+    }
+
     pub fn set_return_type(&mut self, ty: Type) {
         self.return_type = ty;
     }
@@ -57,14 +83,43 @@ impl<Id: Clone + Hash + Eq> Signature<Id> {
         self.total
     }
 
+    #[inline]
+    pub fn matches_cases(&self) -> bool {
+        self.matches_cases
+    }
+
     pub fn arg(&self, name: Id) -> Option<&Argument<Id>> {
-        self.args.iter().filter(|spec| spec.name == name).next()
+        self.args.iter().find(|spec| spec.name == name)
     }
 
     #[inline]
-    pub fn args(&self) -> ::std::slice::Iter<Argument<Id>> {
+    pub fn args(&self) -> ::std::slice::Iter<'_, Argument<Id>> {
         self.args.iter()
     }
+
+    /// The same signature with every keyword renamed by `f`.
+    pub fn map_names<To, F>(&self, mut f: F) -> Signature<To>
+    where
+        To: Clone + Hash + Eq,
+        F: FnMut(&Id) -> To,
+    {
+        Signature {
+            args: self
+                .args
+                .iter()
+                .map(|arg| Argument {
+                    name: f(&arg.name),
+                    ty: arg.ty.clone(),
+                    variadic: arg.variadic,
+                    required: arg.required,
+                    partial: arg.partial,
+                })
+                .collect(),
+            return_type: self.return_type.clone(),
+            total: self.total,
+            matches_cases: self.matches_cases,
+        }
+    }
 }
 
 impl<Id: Clone + Hash + Eq + Display> Display for Signature<Id> {
@@ -72,6 +127,7 @@ impl<Id: Clone + Hash + Eq + Display> Display for Signature<Id> {
         use std::fmt::Write;
         let mut first = true;
         for arg in self.args.iter() {
+            let partial = if first { !self.total } else { arg.partial };
             if first {
                 first = false;
             } else {
@@ -87,9 +143,158 @@ impl<Id: Clone + Hash + Eq + Display> Display for Signature<Id> {
             if !arg.required {
                 f.write_char('?')?;
             }
+            if partial {
+                f.write_char('!')?;
+            }
             f.write_str(": ")?;
             write!(f, "{}", arg.ty)?;
         }
         write!(f, " :: {}", self.return_type)
     }
 }
+
+impl FromStr for Signature<String> {
+    type Err = String;
+
+    /// Parse a signature written like `Display` writes one.
+    fn from_str(text: &str) -> Result<Signature<String>, String> {
+        let arrow = *unquoted_indices(text, "::")
+            .last()
+            .ok_or("a signature needs `::` before its return type")?;
+        let (args_text, return_text) = (&text[..arrow], &text[arrow + 2..]);
+
+        // types only contain colons inside the strings of enums, so each other colon ends a
+        // keyword that starts after a space
+        let colons = unquoted_indices(args_text, ":");
+        let starts: Vec<usize> = colons
+            .iter()
+            .map(|&colon| {
+                args_text[..colon]
+                    .rfind(char::is_whitespace)
+                    .map_or(0, |space| space + 1)
+            })
+            .collect();
+        if colons.is_empty() || !args_text[..starts[0]].trim().is_empty() {
+            return Err(String::from("a signature must start with a keyword"));
+        }
+
+        let mut signature = Signature::with_capacity(colons.len());
+        for (i, (&start, &colon)) in starts.iter().zip(&colons).enumerate() {
+            let head = &args_text[start..colon];
+            let type_end = starts.get(i + 1).cloned().unwrap_or(args_text.len());
+            let ty = args_text[colon + 1..type_end]
+                .parse()
+                .map_err(|err| format!("`{}:` {}", head, err))?;
+
+            let mut name = head;
+            let partial = name.ends_with('!');
+            name = name.trim_end_matches('!');
+            let required = !name.ends_with('?');
+            name = name.trim_end_matches('?');
+            let variadic = name.starts_with('[') && name.ends_with(']');
+            if variadic {
+                name = &name[1..name.len() - 1];
+            }
+            if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                return Err(format!("`{}:` is not a keyword", head));
+            }
+            if signature.arg(String::from(name)).is_some() {
+                return Err(format!("`{}:` is given twice", name));
+            }
+
+            if i == 0 {
+                if !required {
+                    return Err(format!(
+                        "`{}:` names the function, so it can't be optional",
+                        name
+                    ));
+                }
+                signature.set_total(!partial);
+            }
+            signature.add_argument(Argument {
+                name: String::from(name),
+                ty,
+                variadic,
+                required,
+                partial: partial && i > 0,
+            });
+        }
+        signature.set_return_type(
+            return_text
+                .parse()
+                .map_err(|err| format!("return type {}", err))?,
+        );
+        Ok(signature)
+    }
+}
+
+/// Where `pattern` appears in `text` outside of double-quoted strings.
+fn unquoted_indices(text: &str, pattern: &str) -> Vec<usize> {
+    let mut indices = Vec::new();
+    let (mut quoted, mut escaped) = (false, false);
+    for (i, c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            _ if !quoted && text[i..].starts_with(pattern) => indices.push(i),
+            _ => {}
+        }
+    }
+    indices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trips_with_display() {
+        let sources = [
+            "each: [ In... ] do: { In => Out } :: [ Out... ]",
+            "calc: number [plus]?: number [dividedBy]?!: number :: number",
+            "crash!: string :: any",
+            "if: boolean [and]?: { boolean } [or]: { boolean } :: [ a=A b?=B ]",
+            "pick: < \"to:do\" | \"done\" > :: string",
+            "say: < \"a \\\"b\\\" :: c\" > :: < \"x::y\" >",
+        ];
+        for src in sources.iter() {
+            let signature: Signature<String> = src.parse().unwrap();
+            assert_eq!(&signature.to_string(), src);
+        }
+
+        let signature: Signature<String> = "countFrom:number  to: number\n by?:number::[number]"
+            .parse()
+            .unwrap();
+        assert!(signature.is_total());
+        assert_eq!(
+            signature.to_string(),
+            "countFrom: number to: number by?: number :: [ number... ]"
+        );
+    }
+
+    #[test]
+    fn test_reports_errors() {
+        let parse = |src: &str| src.parse::<Signature<String>>().map(|_| ());
+        assert_eq!(
+            parse("upperCase: string"),
+            Err("a signature needs `::` before its return type".into())
+        );
+        assert_eq!(
+            parse("string :: string"),
+            Err("a signature must start with a keyword".into())
+        );
+        assert_eq!(
+            parse("with?: A :: A"),
+            Err("`with:` names the function, so it can't be optional".into())
+        );
+        assert_eq!(
+            parse("a: number a: number :: number"),
+            Err("`a:` is given twice".into())
+        );
+        assert_eq!(
+            parse("a: [ number :: number"),
+            Err("`a:` unexpected end of type".into())
+        );
+    }
+}
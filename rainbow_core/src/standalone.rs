@@ -1,8 +1,10 @@
 use std::collections::HashMap;
 use std::fmt::{Display, Error as FmtError, Formatter};
 use std::iter::FromIterator;
+use std::time::Duration;
 
-use crate::interpreter::{Block, List as IList, Record as IRecord, Value as IValue};
+use crate::decimal::Decimal;
+use crate::interpreter::{Block, List as IList, OpaqueValue, Record as IRecord, Value as IValue};
 use crate::primitive::Prim;
 use crate::typing::Type;
 use crate::with_error::WithError;
@@ -13,19 +15,33 @@ pub enum Value {
     List(Vec<Value>),
     Record(HashMap<String, Value>),
     Block(Block),
+    Absent,
+    Variant(String, Option<Box<Value>>),
+    Opaque(String, u64),
 }
 
 impl Value {
+    /// The value of something that might be absent, when it is.
+    pub fn absent() -> Value {
+        Value::Absent
+    }
+
+    /// A value tagged as one of the cases of a union, with the payload of that case if it has one.
+    pub fn variant(tag: String, payload: Option<Value>) -> Value {
+        Value::Variant(tag, payload.map(Box::new))
+    }
+
     pub fn type_of(&self) -> Type {
         use crate::primitive::Prim;
         match *self {
-            Value::Prim(Prim::Number(_)) => Type::Num,
+            Value::Prim(Prim::Number(_)) | Value::Prim(Prim::Decimal(_)) => Type::Num,
             Value::Prim(Prim::String(_)) => Type::Str,
             Value::Prim(Prim::Boolean(_)) => Type::Bool,
             Value::Prim(Prim::Time(_)) => Type::Time,
-            Value::Prim(Prim::Money(_, _)) => Type::Money,
+            Value::Prim(Prim::Duration(_)) => Type::Duration,
+            Value::Prim(Prim::Money(ref currency, _)) => Type::money_in(currency),
             Value::List(ref items) => {
-                if items.len() == 0 {
+                if items.is_empty() {
                     Type::list_of(Type::Any)
                 } else {
                     Type::list_of(items[0].type_of())
@@ -35,6 +51,12 @@ impl Value {
                 Type::record_from_iter(fields.iter().map(|(k, v)| (k.clone(), v.type_of())))
             }
             Value::Block(_) => Type::block_from_to(vec![], Type::Any),
+            Value::Absent => Type::optional(Type::Any),
+            Value::Variant(ref tag, ref payload) => Type::union_from_iter(vec![(
+                tag.clone(),
+                payload.as_ref().map(|payload| payload.type_of()),
+            )]),
+            Value::Opaque(ref name, _) => Type::Opaque(name.clone()),
         }
     }
 }
@@ -63,6 +85,17 @@ impl IValue for Value {
     fn try_number(&self) -> Result<f64, String> {
         match *self {
             Value::Prim(Prim::Number(f)) => Ok(f),
+            Value::Prim(Prim::Decimal(ref d)) => Ok(d.to_f64()),
+            _ => Err(format!("{} is not a number", self)),
+        }
+    }
+
+    fn try_decimal(&self) -> Result<Decimal, String> {
+        match *self {
+            Value::Prim(Prim::Number(f)) => {
+                Decimal::from_f64(f).ok_or_else(|| format!("{} is not a finite number", f))
+            }
+            Value::Prim(Prim::Decimal(ref d)) => Ok(d.clone()),
             _ => Err(format!("{} is not a number", self)),
         }
     }
@@ -81,23 +114,28 @@ impl IValue for Value {
         }
     }
 
-    /*
-    fn try_money(&self) -> Result<(String, f64), String> {
+    fn try_duration(&self) -> Result<Duration, String> {
         match *self {
-            Value::Prim(Prim::Money(ref currency, amount)) => Ok((currency.clone(), amount)),
-            _ => Err(format!("{} is not a money", self))
+            Value::Prim(Prim::Duration(d)) => Ok(d),
+            _ => Err(format!("{} is not a duration", self)),
         }
     }
-    */
 
-    fn try_list(&self) -> Result<Self::List, String> {
+    fn try_money(&self) -> Result<(&str, &Decimal), String> {
+        match *self {
+            Value::Prim(Prim::Money(ref currency, ref amount)) => Ok((currency, amount)),
+            _ => Err(format!("{} is not an amount of money", self)),
+        }
+    }
+
+    fn try_list(&self) -> Result<<Self as IValue>::List, String> {
         match *self {
             Value::List(ref list) => Ok(list.clone()),
             _ => Err(format!("{} is not a list", self)),
         }
     }
 
-    fn try_record(&self) -> Result<Self::Record, String> {
+    fn try_record(&self) -> Result<<Self as IValue>::Record, String> {
         match *self {
             Value::Record(ref map) => Ok(map.clone()),
             _ => Err(format!("{} is not a list", self)),
@@ -110,6 +148,38 @@ impl IValue for Value {
             _ => Err(format!("{} is not a block", self)),
         }
     }
+
+    fn is_absent(&self) -> bool {
+        *self == Value::Absent
+    }
+
+    fn try_variant(&self) -> Result<(&str, Option<Value>), String> {
+        match *self {
+            Value::Variant(ref tag, ref payload) => {
+                Ok((tag, payload.as_ref().map(|payload| (**payload).clone())))
+            }
+            _ => Err(format!("{} is not one of the cases of a union", self)),
+        }
+    }
+
+    fn try_opaque(&self) -> Result<&str, String> {
+        self.try_handle().map(|(name, _)| name)
+    }
+}
+
+impl OpaqueValue for Value {
+    type Handle = u64;
+
+    fn opaque(name: String, handle: u64) -> Value {
+        Value::Opaque(name, handle)
+    }
+
+    fn try_handle(&self) -> Result<(&str, &u64), String> {
+        match *self {
+            Value::Opaque(ref name, ref handle) => Ok((name, handle)),
+            _ => Err(format!("{} is not a host value", self)),
+        }
+    }
 }
 
 impl From<bool> for Value {
@@ -124,7 +194,7 @@ impl From<String> for Value {
     }
 }
 
-impl<'a> From<&'a str> for Value {
+impl From<&str> for Value {
     fn from(s: &str) -> Value {
         Value::Prim(Prim::String(s.into()))
     }
@@ -136,6 +206,24 @@ impl From<u64> for Value {
     }
 }
 
+impl From<Duration> for Value {
+    fn from(d: Duration) -> Value {
+        Value::Prim(Prim::Duration(d))
+    }
+}
+
+impl From<(String, Decimal)> for Value {
+    fn from((currency, amount): (String, Decimal)) -> Value {
+        Value::Prim(Prim::Money(currency, amount))
+    }
+}
+
+impl From<Decimal> for Value {
+    fn from(d: Decimal) -> Value {
+        Value::Prim(Prim::Decimal(d))
+    }
+}
+
 impl From<f64> for Value {
     fn from(f: f64) -> Value {
         Value::Prim(Prim::Number(f))
@@ -188,7 +276,7 @@ impl Display for Value {
             Prim(ref p) => write!(f, "{}", p),
             List(ref items) => {
                 write!(f, "[ ")?;
-                for item in items.into_iter() {
+                for item in items.iter() {
                     write!(f, "{} ", item)?;
                 }
                 write!(f, "]")
@@ -202,6 +290,10 @@ impl Display for Value {
                 f.write_char(']')
             }
             Block(ref block) => write!(f, "{:?}", block),
+            Absent => f.write_str("absent"),
+            Variant(ref tag, None) => f.write_str(tag),
+            Variant(ref tag, Some(ref payload)) => write!(f, "{}({})", tag, payload),
+            Opaque(ref name, handle) => write!(f, "<{} {}>", name, handle),
         }
     }
 }
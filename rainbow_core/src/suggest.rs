@@ -0,0 +1,88 @@
+//! "Did you mean" suggestions for misspelled names.
+
+/// Find the candidate closest to `word`, if any is close enough to be a plausible typo.
+///
+/// Differences in case alone always count as a match, so `countfrom` suggests `countFrom`.
+pub fn did_you_mean<'a, I>(word: &str, candidates: I) -> Option<String>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let lower_word = word.to_lowercase();
+    let max_distance = ::std::cmp::max(1, word.chars().count() / 3);
+
+    let mut best: Option<(usize, &str)> = None;
+    for candidate in candidates {
+        if candidate == word {
+            continue;
+        }
+        let distance = edit_distance(&lower_word, &candidate.to_lowercase());
+        if distance > max_distance {
+            continue;
+        }
+        best = match best {
+            Some((best_distance, best_candidate))
+                if (best_distance, best_candidate) <= (distance, candidate) =>
+            {
+                Some((best_distance, best_candidate))
+            }
+            _ => Some((distance, candidate)),
+        };
+    }
+    best.map(|(_, candidate)| candidate.to_string())
+}
+
+/// Edit distance between two strings counted in chars, where an insertion, deletion,
+/// substitution or swap of two adjacent chars each cost one edit.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // rows[i][j] is the distance between the first `i` chars of `a` and first `j` chars of `b`
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            let mut distance = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("plus", "pluss"), 1);
+        assert_eq!(edit_distance("abc", ""), 3);
+        assert_eq!(edit_distance("each", "eahc"), 1);
+    }
+
+    #[test]
+    fn test_did_you_mean() {
+        let names = vec!["countFrom", "calc", "compare", "each"];
+        assert_eq!(
+            did_you_mean("countfrom", names.clone()),
+            Some("countFrom".to_string())
+        );
+        assert_eq!(did_you_mean("eahc", names.clone()), Some("each".into()));
+        assert_eq!(did_you_mean("cals", names.clone()), Some("calc".into()));
+        assert_eq!(did_you_mean("frobnicate", names.clone()), None);
+        assert_eq!(did_you_mean("each", names), None);
+    }
+}
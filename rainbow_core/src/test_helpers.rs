@@ -8,11 +8,11 @@ pub fn init_namespace() -> Namespace<Value> {
 }
 
 pub fn parse_with_prelude<'a>(src: &'a str) -> frontend::SyntaxTree<'a> {
-    frontend::parse(&init_namespace(), frontend::Rule::term, src).unwrap()
+    frontend::parse(&init_namespace(), src).unwrap()
 }
 
 pub fn parse<'i>(functions: &Namespace<Value>, expr: &'i str) -> frontend::SyntaxTree<'i> {
-    frontend::parse(functions, frontend::Rule::term, expr).unwrap()
+    frontend::parse(functions, expr).unwrap()
 }
 
 pub fn compile_with_prelude<'a>(src: &'a str) -> interpreter::Script<'a, Value> {
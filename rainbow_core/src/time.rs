@@ -0,0 +1,425 @@
+//! Calendar arithmetic for times and durations, always in UTC.
+//!
+//! A time is a number of milliseconds since 1970-01-01T00:00:00Z. Dates are converted with the
+//! proleptic Gregorian calendar, so no timezone database is needed.
+
+use std::time::Duration;
+
+const MS_PER_SECOND: u64 = 1000;
+const MS_PER_MINUTE: u64 = 60 * MS_PER_SECOND;
+const MS_PER_HOUR: u64 = 60 * MS_PER_MINUTE;
+const MS_PER_DAY: u64 = 24 * MS_PER_HOUR;
+
+/// The parts of a time in UTC.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DateTime {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    pub millisecond: u32,
+}
+
+impl DateTime {
+    pub fn from_millis(time: u64) -> Self {
+        let (year, month, day) = civil_from_days((time / MS_PER_DAY) as i64);
+        let in_day = time % MS_PER_DAY;
+        DateTime {
+            year,
+            month,
+            day,
+            hour: (in_day / MS_PER_HOUR) as u32,
+            minute: (in_day % MS_PER_HOUR / MS_PER_MINUTE) as u32,
+            second: (in_day % MS_PER_MINUTE / MS_PER_SECOND) as u32,
+            millisecond: (in_day % MS_PER_SECOND) as u32,
+        }
+    }
+
+    /// Milliseconds since the epoch, or `None` if this is before 1970.
+    pub fn to_millis(self) -> Option<u64> {
+        let days = days_from_civil(self.year, self.month, self.day);
+        if days < 0 {
+            return None;
+        }
+        Some(
+            days as u64 * MS_PER_DAY
+                + self.hour as u64 * MS_PER_HOUR
+                + self.minute as u64 * MS_PER_MINUTE
+                + self.second as u64 * MS_PER_SECOND
+                + self.millisecond as u64,
+        )
+    }
+}
+
+/// The start of the UTC day containing `time`.
+pub fn start_of_day(time: u64) -> u64 {
+    time - time % MS_PER_DAY
+}
+
+/// The start of the UTC month containing `time`.
+pub fn start_of_month(time: u64) -> u64 {
+    let (year, month, _) = civil_from_days((time / MS_PER_DAY) as i64);
+    days_from_civil(year, month, 1) as u64 * MS_PER_DAY
+}
+
+/// Parse an ISO-8601 date like `2024-03-15` or date and time like `2024-03-15T09:30:00.250+01:00`.
+///
+/// Seconds, milliseconds and the UTC offset are optional, a time without an offset is in UTC.
+pub fn parse_time(text: &str) -> Result<u64, String> {
+    let invalid = || {
+        format!(
+            "`{}` is not a valid time, expected something like `2024-03-15` or `2024-03-15T09:30:00Z`",
+            text
+        )
+    };
+    let mut reader = Reader { rest: text };
+    let year = reader.digits(4).ok_or_else(invalid)? as i64;
+    let month = reader.tag("-").and_then(|_| reader.digits(2));
+    let day = reader.tag("-").and_then(|_| reader.digits(2));
+    let (month, day) = match (month, day) {
+        (Some(month), Some(day)) if valid_date(year, month, day) => (month, day),
+        _ => return Err(invalid()),
+    };
+    let mut time = DateTime {
+        year,
+        month,
+        day,
+        hour: 0,
+        minute: 0,
+        second: 0,
+        millisecond: 0,
+    };
+    let mut offset_minutes: i64 = 0;
+    if reader.tag("T").is_some() {
+        time.hour = reader.digits(2).filter(|&h| h < 24).ok_or_else(invalid)?;
+        reader.tag(":").ok_or_else(invalid)?;
+        time.minute = reader.digits(2).filter(|&m| m < 60).ok_or_else(invalid)?;
+        if reader.tag(":").is_some() {
+            time.second = reader.digits(2).filter(|&s| s < 60).ok_or_else(invalid)?;
+            if reader.tag(".").is_some() {
+                time.millisecond = reader.digits(3).ok_or_else(invalid)?;
+            }
+        }
+        if reader.tag("Z").is_none() {
+            let sign = if reader.tag("+").is_some() {
+                Some(1)
+            } else if reader.tag("-").is_some() {
+                Some(-1)
+            } else {
+                None
+            };
+            if let Some(sign) = sign {
+                let hours = reader.digits(2).filter(|&h| h < 24).ok_or_else(invalid)?;
+                reader.tag(":").ok_or_else(invalid)?;
+                let minutes = reader.digits(2).filter(|&m| m < 60).ok_or_else(invalid)?;
+                offset_minutes = sign * (hours as i64 * 60 + minutes as i64);
+            }
+        }
+    }
+    if !reader.rest.is_empty() {
+        return Err(invalid());
+    }
+    let local = time.to_millis().map(|ms| ms as i64).unwrap_or(-1);
+    let utc = local - offset_minutes * MS_PER_MINUTE as i64;
+    if local < 0 || utc < 0 {
+        return Err(format!("`{}` is before 1970, which is not supported", text));
+    }
+    Ok(utc as u64)
+}
+
+/// Format a time as ISO-8601 in UTC, like `2024-03-15T09:30:00Z`. Milliseconds are only
+/// included when there are some.
+pub fn format_time(time: u64) -> String {
+    if time.is_multiple_of(MS_PER_SECOND) {
+        format_time_with(time, "%Y-%m-%dT%H:%M:%SZ")
+    } else {
+        format_time_with(time, "%Y-%m-%dT%H:%M:%S.%LZ")
+    }
+}
+
+/// Format a time in UTC following `pattern`, where `%Y`, `%m`, `%d`, `%H`, `%M`, `%S` and `%L`
+/// are replaced by the year, month, day, hour, minute, second and millisecond, and `%%` by a
+/// percent sign. Anything else is copied as it is.
+pub fn format_time_with(time: u64, pattern: &str) -> String {
+    let parts = DateTime::from_millis(time);
+    let mut out = String::with_capacity(pattern.len() + 8);
+    let mut chars = pattern.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '%' {
+            out.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => out.push_str(&format!("{:04}", parts.year)),
+            Some('m') => out.push_str(&format!("{:02}", parts.month)),
+            Some('d') => out.push_str(&format!("{:02}", parts.day)),
+            Some('H') => out.push_str(&format!("{:02}", parts.hour)),
+            Some('M') => out.push_str(&format!("{:02}", parts.minute)),
+            Some('S') => out.push_str(&format!("{:02}", parts.second)),
+            Some('L') => out.push_str(&format!("{:03}", parts.millisecond)),
+            Some('%') => out.push('%'),
+            Some(other) => {
+                out.push('%');
+                out.push(other);
+            }
+            None => out.push('%'),
+        }
+    }
+    out
+}
+
+/// Parse an ISO-8601 duration made of weeks, days, hours, minutes and seconds, like `P1W`,
+/// `P2DT12H` or `PT1.5S`. Years and months are not allowed because their length varies.
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+    let invalid = || {
+        format!(
+            "`{}` is not a valid duration, expected something like `P1D` or `PT1H30M`",
+            text
+        )
+    };
+    let mut reader = Reader { rest: text };
+    reader.tag("P").ok_or_else(invalid)?;
+    let mut millis: u64 = 0;
+    let mut components = 0;
+    let mut add = |amount: u64, unit: u64| -> Result<(), String> {
+        components += 1;
+        millis = amount
+            .checked_mul(unit)
+            .and_then(|ms| ms.checked_add(millis))
+            .ok_or_else(invalid)?;
+        Ok(())
+    };
+    for &(unit, ms) in [("W", 7 * MS_PER_DAY), ("D", MS_PER_DAY)].iter() {
+        if let Some(amount) = reader.number_before(unit) {
+            add(amount, ms)?;
+        }
+    }
+    if reader.tag("T").is_some() {
+        let before = reader.rest.len();
+        for &(unit, ms) in [("H", MS_PER_HOUR), ("M", MS_PER_MINUTE)].iter() {
+            if let Some(amount) = reader.number_before(unit) {
+                add(amount, ms)?;
+            }
+        }
+        if let Some(seconds) = reader.seconds() {
+            add(seconds, 1)?;
+        }
+        if reader.rest.len() == before {
+            return Err(invalid());
+        }
+    }
+    if components == 0 || !reader.rest.is_empty() {
+        return Err(invalid());
+    }
+    Ok(Duration::from_millis(millis))
+}
+
+/// Format a duration as ISO-8601, like `P1DT2H30M`. Days are never combined into weeks.
+pub fn format_duration(duration: &Duration) -> String {
+    let millis = duration.as_millis() as u64;
+    let days = millis / MS_PER_DAY;
+    let hours = millis % MS_PER_DAY / MS_PER_HOUR;
+    let minutes = millis % MS_PER_HOUR / MS_PER_MINUTE;
+    let seconds = millis % MS_PER_MINUTE / MS_PER_SECOND;
+    let fraction = millis % MS_PER_SECOND;
+
+    let mut out = String::from("P");
+    if days > 0 {
+        out.push_str(&format!("{}D", days));
+    }
+    if hours > 0 || minutes > 0 || seconds > 0 || fraction > 0 || days == 0 {
+        out.push('T');
+        if hours > 0 {
+            out.push_str(&format!("{}H", hours));
+        }
+        if minutes > 0 {
+            out.push_str(&format!("{}M", minutes));
+        }
+        if fraction > 0 {
+            let fraction = format!("{:03}", fraction);
+            out.push_str(&format!("{}.{}S", seconds, fraction.trim_end_matches('0')));
+        } else if seconds > 0 || out == "PT" {
+            out.push_str(&format!("{}S", seconds));
+        }
+    }
+    out
+}
+
+/// The number of milliseconds in `duration`, which is how much it moves a time.
+pub fn duration_millis(duration: &Duration) -> u64 {
+    duration.as_millis() as u64
+}
+
+struct Reader<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Reader<'a> {
+    fn tag(&mut self, tag: &str) -> Option<()> {
+        self.rest = self.rest.strip_prefix(tag)?;
+        Some(())
+    }
+
+    /// Exactly `count` ASCII digits.
+    fn digits(&mut self, count: usize) -> Option<u32> {
+        let digits = self.rest.get(..count)?;
+        if !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        self.rest = &self.rest[count..];
+        digits.parse().ok()
+    }
+
+    /// One or more digits followed by `unit`.
+    fn number_before(&mut self, unit: &str) -> Option<u64> {
+        let len = self.rest.bytes().take_while(u8::is_ascii_digit).count();
+        if len == 0 || !self.rest[len..].starts_with(unit) {
+            return None;
+        }
+        let number = self.rest[..len].parse().ok()?;
+        self.rest = &self.rest[len + unit.len()..];
+        Some(number)
+    }
+
+    /// Seconds with up to three decimal places followed by `S`, in milliseconds.
+    fn seconds(&mut self) -> Option<u64> {
+        let whole = self.rest.bytes().take_while(u8::is_ascii_digit).count();
+        if whole == 0 {
+            return None;
+        }
+        let mut len = whole;
+        let mut fraction = 0;
+        if self.rest[len..].starts_with('.') {
+            let digits = self.rest[len + 1..]
+                .bytes()
+                .take_while(u8::is_ascii_digit)
+                .count();
+            if digits == 0 || digits > 3 {
+                return None;
+            }
+            let padded = format!("{:0<3}", &self.rest[len + 1..len + 1 + digits]);
+            fraction = padded.parse::<u64>().ok()?;
+            len += 1 + digits;
+        }
+        if !self.rest[len..].starts_with('S') {
+            return None;
+        }
+        let seconds: u64 = self.rest[..whole].parse().ok()?;
+        self.rest = &self.rest[len + 1..];
+        seconds
+            .checked_mul(MS_PER_SECOND)
+            .and_then(|ms| ms.checked_add(fraction))
+    }
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn valid_date(year: i64, month: u32, day: u32) -> bool {
+    let days_in_month = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        _ => return false,
+    };
+    day >= 1 && day <= days_in_month
+}
+
+/// Days since 1970-01-01 of a date, using Howard Hinnant's `days_from_civil`.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The date of a number of days since 1970-01-01, the inverse of `days_from_civil`.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = if days >= 0 { days } else { days - 146_096 } / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_format_times() {
+        assert_eq!(parse_time("1970-01-01"), Ok(0));
+        assert_eq!(parse_time("2024-02-29"), Ok(1_709_164_800_000));
+        assert_eq!(
+            parse_time("2024-02-29T13:05:09.250Z"),
+            Ok(1_709_164_800_000 + 13 * MS_PER_HOUR + 5 * MS_PER_MINUTE + 9_250)
+        );
+        assert_eq!(
+            parse_time("2024-03-01T01:30+02:00"),
+            parse_time("2024-02-29T23:30")
+        );
+        for text in [
+            "2024-02-29T13:05:09.250Z",
+            "1999-12-31T23:59:59Z",
+            "2000-01-01T00:00:00Z",
+        ]
+        .iter()
+        {
+            assert_eq!(format_time(parse_time(text).unwrap()), *text);
+        }
+
+        for text in ["2023-02-29", "2024-1-01", "2024-01-01T25:00", "2024-01-01x"].iter() {
+            assert!(parse_time(text).unwrap_err().contains("not a valid time"));
+        }
+        assert_eq!(
+            parse_time("1969-12-31"),
+            Err("`1969-12-31` is before 1970, which is not supported".into())
+        );
+    }
+
+    #[test]
+    fn test_truncate_and_format_with_pattern() {
+        let time = parse_time("2024-03-15T09:30:00Z").unwrap();
+        assert_eq!(format_time(start_of_day(time)), "2024-03-15T00:00:00Z");
+        assert_eq!(format_time(start_of_month(time)), "2024-03-01T00:00:00Z");
+        assert_eq!(
+            format_time_with(time, "%d/%m/%Y %H:%M %% %q"),
+            "15/03/2024 09:30 % %q"
+        );
+    }
+
+    #[test]
+    fn test_parse_and_format_durations() {
+        let cases = [
+            ("P1W", "P7D"),
+            ("P2DT12H", "P2DT12H"),
+            ("PT90M", "PT1H30M"),
+            ("PT1.5S", "PT1.5S"),
+            ("PT0S", "PT0S"),
+        ];
+        for &(text, formatted) in cases.iter() {
+            assert_eq!(format_duration(&parse_duration(text).unwrap()), formatted);
+        }
+        for text in ["P", "PT", "P1H", "P1Y", "PT1D", "PT1.2345S", "PTotal"].iter() {
+            assert!(parse_duration(text).is_err(), "{}", text);
+        }
+    }
+}
@@ -1,22 +1,51 @@
 use crate::namespace::INamespace;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
+use crate::arena::ArenaId;
 use crate::frontend::{NodeData, SyntaxTree};
+use crate::primitive::Prim;
+use crate::signature::Signature;
+use crate::suggest::did_you_mean;
 use id_tree::NodeId;
 
+use super::constraint_solver::Solver;
 use super::substitution::*;
 use super::type_env::TypeEnv;
 use super::type_errors::*;
 use super::types::*;
 
+/// The name of the function whose first argument may contain partial expressions.
+const TRY: &str = "try";
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct Constraint(pub Type, pub Type, pub NodeData);
 
+/// A check that can only be made once all constraints have been solved.
+#[derive(Debug, PartialEq, Clone)]
+pub enum DeferredCheck {
+    /// A variable path read outside of a `try:` block, none of its fields may be optional.
+    RequiredPath {
+        root: Type,
+        path: Vec<String>,
+        location: NodeData,
+    },
+    /// A term interpolated into a string, it must be a string, number or boolean.
+    Interpolated { ty: Type, location: NodeData },
+    /// A string literal given as an argument, it must be one of the values of any enum among the
+    /// type of its parameter and the arguments that share that type.
+    Literal {
+        expected: Vec<Type>,
+        value: String,
+        location: NodeData,
+    },
+}
+
+/// Generate the constraints on the types in `tree` and solve them.
 pub fn generate<NS>(
     ns: &NS,
     type_env: &mut TypeEnv,
     tree: &SyntaxTree,
-) -> (Type, Vec<Constraint>, Vec<TypeError>)
+) -> (Type, Subst, Vec<DeferredCheck>, Vec<TypeError>)
 where
     NS: INamespace,
 {
@@ -24,7 +53,38 @@ where
     let root_node_id = tree.nodes.root_node_id().unwrap();
     let inferred_type = generator.recur(type_env, root_node_id);
     generator.sort_constraints();
-    (inferred_type, generator.constraints, generator.errors)
+
+    #[cfg(test)]
+    {
+        dbg!("constraints:");
+        for Constraint(lft, rgt, _) in generator.constraints.iter() {
+            dbg!("  {} ~ {}", lft, rgt);
+        }
+    }
+
+    generator.solve_so_far();
+
+    // each match adds constraints of its own, which the ones after it may depend on
+    for found in std::mem::take(&mut generator.matches) {
+        generator.check_match(found);
+        generator.solve_so_far();
+    }
+    (
+        inferred_type,
+        generator.solver.finish(),
+        generator.deferred,
+        generator.errors,
+    )
+}
+
+/// A call to a function that matches on the cases of a union, to be checked once the type of
+/// what it matches on is known.
+struct Match {
+    subject: Type,
+    /// The block for each case, in order of their names.
+    blocks: Vec<(String, Type)>,
+    out: Type,
+    location: NodeData,
 }
 
 struct ConstraintGenerator<'a, 'i, NS: INamespace + 'a> {
@@ -32,18 +92,25 @@ struct ConstraintGenerator<'a, 'i, NS: INamespace + 'a> {
     tree: &'i SyntaxTree<'i>,
     fresh_vars: FreshVarSupply,
     inside_try: bool,
+    /// The constraints that haven't been solved yet.
     constraints: Vec<Constraint>,
+    solver: Solver,
+    matches: Vec<Match>,
+    deferred: Vec<DeferredCheck>,
     errors: Vec<TypeError>,
 }
 
 impl<'a, 'i, NS: INamespace> ConstraintGenerator<'a, 'i, NS> {
     fn new(functions: &'a NS, tree: &'i SyntaxTree) -> Self {
         ConstraintGenerator {
-            functions: functions,
-            tree: tree,
+            functions,
+            tree,
             fresh_vars: FreshVarSupply { count: 0 },
             inside_try: false,
             constraints: Vec::with_capacity(1024),
+            solver: Solver::new(),
+            matches: Vec::new(),
+            deferred: Vec::new(),
             errors: Vec::with_capacity(100),
         }
     }
@@ -52,6 +119,13 @@ impl<'a, 'i, NS: INamespace> ConstraintGenerator<'a, 'i, NS> {
         self.constraints.push(Constraint(ty1, ty2, node_data));
     }
 
+    /// Solve the constraints generated since the last time, to find what is known so far.
+    fn solve_so_far(&mut self) -> &Solver {
+        let constraints = std::mem::take(&mut self.constraints);
+        self.solver.solve(constraints, &mut self.errors);
+        &self.solver
+    }
+
     fn add_constraint_at(&mut self, node_id: &NodeId, ty1: Type, ty2: Type) {
         self.add_constraint(
             self.tree.nodes.get(node_id).unwrap().data().clone(),
@@ -67,15 +141,43 @@ impl<'a, 'i, NS: INamespace> ConstraintGenerator<'a, 'i, NS> {
         let data = node.data();
         dbg!("infer {:?}", node.data());
         match data.node_type {
-            Root => node
-                .children()
-                .into_iter()
-                .fold(Type::Any, |_, child_id| self.recur(type_env, child_id)),
+            Root => {
+                // bindings are kept out of the root scope, which only holds the inputs
+                let mut local_env = type_env.child();
+                let children = node.children();
+                let (result_id, binding_ids) = children.split_last().unwrap();
+                for binding_id in binding_ids {
+                    self.bind(&mut local_env, binding_id);
+                }
+                self.recur(&mut local_env, result_id)
+            }
             Primitive(id) => self.tree.lookup_constant(id).type_of(),
             List => {
+                let elem_types: Vec<Type> = node
+                    .children()
+                    .iter()
+                    .map(|elem_id| self.recur(type_env, elem_id))
+                    .collect();
+
+                // items of different types share the narrowest type they all satisfy, like
+                // records that don't all have the same fields
+                if elem_types.len() > 1 {
+                    let solver = self.solve_so_far();
+                    let solved: Vec<Type> =
+                        elem_types.iter().map(|ty| solver.resolve(ty)).collect();
+                    let upper_bound = solved[1..]
+                        .iter()
+                        .try_fold(solved[0].clone(), |bound, ty| bound.least_upper_bound(ty));
+                    if let Some(upper_bound) = upper_bound {
+                        for (elem_id, elem_ty) in node.children().iter().zip(elem_types) {
+                            self.add_constraint_at(elem_id, elem_ty, upper_bound.clone());
+                        }
+                        return Type::list_of(upper_bound);
+                    }
+                }
+
                 let elem_type_var = self.fresh_vars.next().unwrap();
-                for elem_id in node.children() {
-                    let elem_ty = self.recur(type_env, elem_id);
+                for (elem_id, elem_ty) in node.children().iter().zip(elem_types) {
                     self.add_constraint_at(elem_id, elem_type_var.clone(), elem_ty);
                 }
                 Type::list_of(elem_type_var)
@@ -89,9 +191,9 @@ impl<'a, 'i, NS: INamespace> ConstraintGenerator<'a, 'i, NS> {
                     // each child here is a RecordEntry
                     let name_and_value_ids: Vec<_> =
                         self.tree.nodes.children_ids(entry_id).unwrap().collect();
-                    let field_type = self.recur(type_env, &name_and_value_ids[1]);
+                    let field_type = self.recur(type_env, name_and_value_ids[1]);
                     // todo: check field_name_node.data().node_type !== Ident
-                    let field_name = self.tree.node_id_str(&name_and_value_ids[0]).unwrap();
+                    let field_name = self.tree.node_id_str(name_and_value_ids[0]).unwrap();
                     field_types.insert(
                         String::from(field_name),
                         RecordField::new(field_type, false),
@@ -100,6 +202,55 @@ impl<'a, 'i, NS: INamespace> ConstraintGenerator<'a, 'i, NS> {
                 Type::record_from_map(field_types)
             }
 
+            Update => {
+                let children = node.children();
+                let source_ty = self.recur(type_env, &children[0]);
+                let not_a_record = match self.solve_so_far().resolve(&source_ty) {
+                    Type::Record(..) | Type::Update(..) | Type::Var(_) | Type::Any => None,
+                    other => Some(other),
+                };
+                let source = match not_a_record {
+                    Some(ty) => {
+                        let location = self.tree.node_data(&children[0]).unwrap().clone();
+                        self.errors.push(Problem::NotUpdatable(ty).at(location));
+                        None
+                    }
+                    None => {
+                        self.add_constraint_at(
+                            &children[0],
+                            source_ty.clone(),
+                            Type::Record(true, HashMap::new()),
+                        );
+                        // every field of the source is kept, including the ones only found later,
+                        // unless it is replaced
+                        Some(match source_ty {
+                            Type::Var(name) => name,
+                            source_ty => {
+                                let var = self.fresh_vars.next().unwrap();
+                                self.add_constraint_at(&children[0], source_ty, var.clone());
+                                var.var_name().unwrap().clone()
+                            }
+                        })
+                    }
+                };
+                let mut field_types = HashMap::with_capacity(children.len() - 1);
+                for entry_id in &children[1..] {
+                    let name_and_value_ids: Vec<_> =
+                        self.tree.nodes.children_ids(entry_id).unwrap().collect();
+                    let field_type = self.recur(type_env, name_and_value_ids[1]);
+                    let field_name = self.tree.node_id_str(name_and_value_ids[0]).unwrap();
+                    field_types.insert(
+                        String::from(field_name),
+                        RecordField::new(field_type, false),
+                    );
+                }
+                match source {
+                    Some(source) => Type::Update(source, field_types),
+                    // the fields that are set are all there is to go on
+                    None => Type::record_from_map(field_types),
+                }
+            }
+
             Variable => {
                 let children = node.children();
                 let root_name = self.tree.node_id_str(&children[0]).unwrap();
@@ -136,6 +287,18 @@ impl<'a, 'i, NS: INamespace> ConstraintGenerator<'a, 'i, NS> {
                             next_var
                         });
 
+                if !self.inside_try {
+                    let path = children[1..]
+                        .iter()
+                        .map(|child_id| String::from(self.tree.node_id_str(child_id).unwrap()))
+                        .collect();
+                    self.deferred.push(DeferredCheck::RequiredPath {
+                        root: root_ty.clone(),
+                        path,
+                        location: data.clone(),
+                    });
+                }
+
                 self.add_constraint(data.clone(), root_ty, root_var);
                 leaf_type
             }
@@ -146,12 +309,11 @@ impl<'a, 'i, NS: INamespace> ConstraintGenerator<'a, 'i, NS> {
                     let arg_node_ids = self.tree.nodes.get(&children[0]).unwrap().children();
                     let mut in_types = Vec::with_capacity(arg_node_ids.len());
                     let mut local_env = type_env.child();
-                    for (id, ty) in arg_node_ids.into_iter().zip(&mut self.fresh_vars) {
+                    let mut bound = HashSet::new();
+                    for arg_node_id in arg_node_ids {
+                        let ty = self.fresh_vars.next().unwrap();
                         in_types.push(ty.clone());
-                        local_env.explicitly_define(
-                            String::from(self.tree.node_id_str(id).unwrap()),
-                            ty,
-                        );
+                        self.bind_pattern(&mut local_env, &mut bound, arg_node_id, ty);
                     }
                     (in_types, self.recur(&mut local_env, &children[1]))
                 } else {
@@ -162,38 +324,61 @@ impl<'a, 'i, NS: INamespace> ConstraintGenerator<'a, 'i, NS> {
 
             Apply => {
                 let children = node.children();
-                debug_assert!(children.len() > 0);
+                debug_assert!(!children.is_empty());
 
                 let arg0 = self.tree.nodes.get(&children[0]).unwrap();
                 let func_name = self
                     .tree
                     .node_id_str(&arg0.children()[0])
                     .unwrap()
-                    .trim_right_matches(':');
+                    .trim_end_matches(':');
                 let sig = match self.functions.get_signature(func_name) {
                     None => {
+                        let node_data = self.tree.node_data(&arg0.children()[0]).unwrap().clone();
+                        let functions = self.functions;
+                        let suggestion = did_you_mean(
+                            func_name,
+                            functions
+                                .symbols()
+                                .as_slice()
+                                .iter()
+                                .map(String::as_str)
+                                .filter(|name| functions.get_signature(name).is_some()),
+                        );
                         self.errors.push(
-                            Problem::UnknownFunction.at(self
-                                .tree
-                                .nodes
-                                .get(&children[0])
-                                .unwrap()
-                                .data()
-                                .clone()),
+                            Problem::UnknownFunction {
+                                name: func_name.into(),
+                                suggestion,
+                            }
+                            .at(node_data),
                         );
                         return Type::Any;
                     }
                     Some(s) => s,
                 };
 
+                if !sig.is_total() && !self.inside_try {
+                    let node_data = self.tree.node_data(&arg0.children()[0]).unwrap().clone();
+                    self.errors.push(
+                        Problem::Unguarded(Partial::Function(func_name.into())).at(node_data),
+                    );
+                }
+
+                self.check_arguments(func_name, sig, children, data);
+
                 // create a local substitution for any type variables in the signature
                 let sig_subst: Option<Subst> = sig
                     .args()
-                    .fold(None, |vars, arg| extend_vars(vars, &arg.ty))
+                    .fold(extend_vars(None, sig.returns()), |vars, arg| {
+                        extend_vars(vars, &arg.ty)
+                    })
                     .map(|vars| vars.into_iter().zip(&mut self.fresh_vars).collect());
 
-                for child_id in children {
-                    let arg_children = self.tree.nodes.get(&child_id).unwrap().children();
+                let mut arg_types = Vec::with_capacity(children.len());
+                let mut param_types = Vec::with_capacity(children.len());
+                let mut literals = Vec::new();
+                for (i, child_id) in children.iter().enumerate() {
+                    let arg_children = self.tree.nodes.get(child_id).unwrap().children();
                     let kw_node_data = self.tree.node_data(&arg_children[0]).unwrap();
                     let kw_sym_id = if let Keyword(kw_id) = kw_node_data.node_type {
                         Some(kw_id)
@@ -201,11 +386,41 @@ impl<'a, 'i, NS: INamespace> ConstraintGenerator<'a, 'i, NS> {
                         None
                     };
                     let arg_ty = match kw_sym_id.and_then(|id| sig.arg(id)) {
-                        Some(spec) => spec.ty.clone(),
+                        Some(spec) => {
+                            if spec.partial && !self.inside_try {
+                                self.errors.push(
+                                    Problem::Unguarded(Partial::Argument {
+                                        function: func_name.into(),
+                                        keyword: self
+                                            .functions
+                                            .symbols()
+                                            .resolve(spec.name)
+                                            .clone(),
+                                    })
+                                    .at(kw_node_data.clone()),
+                                );
+                            }
+                            spec.ty.clone()
+                        }
                         None => {
-                            let node_data = self.tree.node_data(&arg_children[0]).unwrap().clone();
-                            self.errors
-                                .push(Problem::UnknownKeyword(func_name.into()).at(node_data));
+                            let keyword: String = self
+                                .tree
+                                .node_str(kw_node_data)
+                                .trim_end_matches(':')
+                                .into();
+                            self.errors.push(
+                                Problem::UnknownKeyword {
+                                    suggestion: did_you_mean(
+                                        &keyword,
+                                        sig.args().map(|arg| {
+                                            self.functions.symbols().resolve(arg.name).as_str()
+                                        }),
+                                    ),
+                                    function: func_name.into(),
+                                    keyword,
+                                }
+                                .at(kw_node_data.clone()),
+                            );
                             Type::Any
                         }
                     };
@@ -215,11 +430,43 @@ impl<'a, 'i, NS: INamespace> ConstraintGenerator<'a, 'i, NS> {
                         None => arg_ty.clone(),
                     };
 
+                    // the first argument to `try:` is the only place partial expressions are allowed
+                    let was_inside_try = self.inside_try;
+                    if i == 0 && func_name == TRY {
+                        self.inside_try = true;
+                    }
                     let stx_type = { self.recur(type_env, &arg_children[1]) };
+                    self.inside_try = was_inside_try;
+                    arg_types.push(stx_type.clone());
+                    param_types.push(arg_type.clone());
+                    if let Some(value) = self.string_literal(&arg_children[1]) {
+                        let location = self.tree.node_data(&arg_children[1]).unwrap().clone();
+                        literals.push((i, value, location));
+                    }
                     self.add_constraint_at(&arg_children[1], stx_type, arg_type);
                 }
 
+                // `equal: "paid" to: status` has to check the literal against `status`
+                for (i, value, location) in literals {
+                    let mut expected = vec![param_types[i].clone()];
+                    for (j, param_type) in param_types.iter().enumerate() {
+                        if j != i && *param_type == param_types[i] {
+                            expected.push(arg_types[j].clone());
+                        }
+                    }
+                    self.deferred.push(DeferredCheck::Literal {
+                        expected,
+                        value,
+                        location,
+                    });
+                }
+
                 let out = self.fresh_vars.next().unwrap();
+                if sig.matches_cases() && arg_types.len() == 2 {
+                    let cases_id = &self.tree.nodes.get(&children[1]).unwrap().children()[1];
+                    let location = self.tree.node_data(cases_id).unwrap().clone();
+                    self.match_cases(&arg_types[0], &arg_types[1], &out, location);
+                }
 
                 let return_type = match sig_subst {
                     Some(ref s) => sig.returns().apply_substitution(s),
@@ -229,18 +476,272 @@ impl<'a, 'i, NS: INamespace> ConstraintGenerator<'a, 'i, NS> {
                 self.add_constraint(data.clone(), out.clone(), return_type);
                 out
             }
+            Concat => {
+                for child_id in node.children() {
+                    let ty = self.recur(type_env, child_id);
+                    let location = self.tree.nodes.get(child_id).unwrap().data().clone();
+                    self.deferred
+                        .push(DeferredCheck::Interpolated { ty, location });
+                }
+                Type::Str
+            }
+            // a hole could be anything, so it can't cause errors in the rest of the script
+            Hole => self.fresh_vars.next().unwrap(),
             // the constraint generator should never visit other node types
             _ => Type::Never,
         }
     }
 
+    /// Infer the type of a binding and define its name for the rest of the script.
+    fn bind(&mut self, type_env: &mut TypeEnv, binding_id: &NodeId) {
+        let children = self.tree.nodes.get(binding_id).unwrap().children();
+        let name = String::from(self.tree.node_id_str(&children[0]).unwrap());
+        let ty = self.recur(type_env, &children[1]);
+
+        let name_data = self.tree.node_data(&children[0]).unwrap().clone();
+        if type_env.is_undefined(&name) {
+            self.errors
+                .push(Problem::UsedBeforeBinding(name.clone()).at(name_data));
+        } else if type_env.contains_key(&name) {
+            self.errors
+                .push(Problem::AlreadyDefined(name.clone()).at(name_data));
+        }
+
+        // solve what is known so far, to find the type variables that only the binding uses
+        type_env.define_generalized(name, ty, self.solve_so_far());
+    }
+
+    /// The text of a string literal without any interpolation.
+    fn string_literal(&self, node_id: &NodeId) -> Option<String> {
+        match self.tree.node_data(node_id).unwrap().node_type {
+            crate::frontend::NodeType::Primitive(id) => match *self.tree.lookup_constant(id) {
+                Prim::String(ref value) => Some(value.clone()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Give every case block of a `match:` the same output as the call. Whether they cover every
+    /// case of the union being matched, and take the payload of their case, is checked once every
+    /// other constraint is solved, so that the union is known.
+    fn match_cases(&mut self, subject: &Type, cases: &Type, out: &Type, location: NodeData) {
+        let mut blocks: Vec<(String, Type)> = match *cases {
+            Type::Record(_, ref fields) => fields
+                .iter()
+                .map(|(tag, field)| (tag.clone(), field.get_type().clone()))
+                .collect(),
+            _ => return,
+        };
+        blocks.sort_by(|(left, _), (right, _)| left.cmp(right));
+
+        for (_, block) in &blocks {
+            if let Type::Block(ref inputs, _) = *block {
+                let expected = Type::block_from_to(inputs.clone(), out.clone());
+                self.add_constraint(location.clone(), block.clone(), expected);
+            }
+        }
+        self.matches.push(Match {
+            subject: subject.clone(),
+            blocks,
+            out: out.clone(),
+            location,
+        });
+    }
+
+    /// Check that the cases of a match cover every case of the union being matched, and that each
+    /// one is a block taking the payload of its case. When the type being matched isn't known to
+    /// be a union, it is the union of the cases given.
+    fn check_match(&mut self, found: Match) {
+        let Match {
+            subject,
+            blocks,
+            out,
+            location,
+        } = found;
+        match self.solver.resolve(&subject) {
+            Type::Union(union_cases) => {
+                let mut missing: Vec<String> = union_cases
+                    .keys()
+                    .filter(|tag| !blocks.iter().any(|(name, _)| name == *tag))
+                    .cloned()
+                    .collect();
+                if !missing.is_empty() {
+                    missing.sort();
+                    self.errors
+                        .push(Problem::NonExhaustive(missing).at(location.clone()));
+                }
+                for (tag, block) in blocks {
+                    match union_cases.get(&tag) {
+                        Some(payload) => {
+                            let expected =
+                                Type::block_from_to(payload.iter().cloned().collect(), out.clone());
+                            self.add_constraint(location.clone(), block, expected);
+                        }
+                        None => {
+                            let suggestion =
+                                did_you_mean(&tag, union_cases.keys().map(String::as_str));
+                            self.errors.push(
+                                Problem::UnknownCase {
+                                    name: tag,
+                                    suggestion,
+                                }
+                                .at(location.clone()),
+                            );
+                        }
+                    }
+                }
+            }
+            _ => {
+                let mut union_cases = HashMap::with_capacity(blocks.len());
+                for (tag, block) in blocks {
+                    let payload = match block {
+                        Type::Block(ref inputs, _) => inputs.first().cloned(),
+                        _ => None,
+                    };
+                    let expected =
+                        Type::block_from_to(payload.iter().cloned().collect(), out.clone());
+                    self.add_constraint(location.clone(), block, expected);
+                    union_cases.insert(tag, payload);
+                }
+                self.add_constraint(location, subject, Type::Union(union_cases));
+            }
+        }
+    }
+
+    /// Define the names in a block argument, constraining `ty` to the shape of its pattern.
+    /// `bound` holds the names the block's arguments have bound so far, each can only be bound once.
+    fn bind_pattern(
+        &mut self,
+        type_env: &mut TypeEnv,
+        bound: &mut HashSet<String>,
+        node_id: &NodeId,
+        ty: Type,
+    ) {
+        use crate::frontend::NodeType::*;
+        let node = self.tree.nodes.get(node_id).unwrap();
+        let data = node.data();
+        match data.node_type {
+            Record => {
+                let mut fields = HashMap::with_capacity(node.children().len());
+                for entry_id in node.children() {
+                    let name_and_pattern_ids: Vec<_> =
+                        self.tree.nodes.children_ids(entry_id).unwrap().collect();
+                    let field_name =
+                        String::from(self.tree.node_id_str(name_and_pattern_ids[0]).unwrap());
+                    let field_ty = self.fresh_vars.next().unwrap();
+                    if !self.inside_try {
+                        self.deferred.push(DeferredCheck::RequiredPath {
+                            root: ty.clone(),
+                            path: vec![field_name.clone()],
+                            location: self.tree.node_data(entry_id).unwrap().clone(),
+                        });
+                    }
+                    fields.insert(
+                        field_name,
+                        RecordField::new(field_ty.clone(), self.inside_try),
+                    );
+                    self.bind_pattern(type_env, bound, name_and_pattern_ids[1], field_ty);
+                }
+                self.add_constraint(data.clone(), ty, Type::Record(true, fields));
+            }
+            List => {
+                if !self.inside_try {
+                    let pattern = self.tree.node_str(data).into();
+                    self.errors
+                        .push(Problem::Unguarded(Partial::ListPattern(pattern)).at(data.clone()));
+                }
+                let elem_ty = self.fresh_vars.next().unwrap();
+                for elem_id in node.children() {
+                    self.bind_pattern(type_env, bound, elem_id, elem_ty.clone());
+                }
+                self.add_constraint(data.clone(), ty, Type::list_of(elem_ty));
+            }
+            _ => {
+                let name = String::from(self.tree.node_id_str(node_id).unwrap());
+                if !bound.insert(name.clone()) {
+                    self.errors
+                        .push(Problem::AlreadyDefined(name.clone()).at(data.clone()));
+                }
+                type_env.explicitly_define(name, ty);
+            }
+        }
+    }
+
+    /// Check the keywords supplied to a call against its signature: every required argument must
+    /// be present, non-variadic arguments can't be repeated, and no argument may follow a required
+    /// argument that the signature declares after it.
+    fn check_arguments(
+        &mut self,
+        func_name: &str,
+        sig: &Signature,
+        arg_node_ids: &[NodeId],
+        location: &NodeData,
+    ) {
+        use crate::frontend::NodeType::Keyword;
+
+        // (position in signature, keyword) of each argument seen so far
+        let mut seen: Vec<(usize, ArenaId)> = Vec::with_capacity(arg_node_ids.len());
+        for arg_node_id in arg_node_ids {
+            let kw_node_id = &self.tree.nodes.get(arg_node_id).unwrap().children()[0];
+            let kw_node_data = self.tree.node_data(kw_node_id).unwrap();
+            let kw_id = match kw_node_data.node_type {
+                Keyword(id) => id,
+                _ => continue,
+            };
+            // unknown keywords are reported while inferring the argument types
+            let (position, spec) = match sig.args().enumerate().find(|&(_, arg)| arg.name == kw_id)
+            {
+                Some(found) => found,
+                None => continue,
+            };
+
+            if !spec.variadic && seen.iter().any(|&(_, id)| id == kw_id) {
+                self.errors.push(
+                    Problem::DuplicateArgument {
+                        function: func_name.into(),
+                        keyword: self.functions.symbols().resolve(kw_id).clone(),
+                    }
+                    .at(kw_node_data.clone()),
+                );
+            }
+
+            let later_required = seen.iter().find(|&&(pos, id)| {
+                pos > position && sig.arg(id).map(|arg| arg.required).unwrap_or(false)
+            });
+            if let Some(&(_, after)) = later_required {
+                self.errors.push(
+                    Problem::ArgumentOrder {
+                        function: func_name.into(),
+                        keyword: self.functions.symbols().resolve(kw_id).clone(),
+                        after: self.functions.symbols().resolve(after).clone(),
+                    }
+                    .at(kw_node_data.clone()),
+                );
+            }
+
+            seen.push((position, kw_id));
+        }
+
+        for arg in sig.args() {
+            if arg.required && !seen.iter().any(|&(_, id)| id == arg.name) {
+                self.errors.push(
+                    Problem::MissingArgument {
+                        function: func_name.into(),
+                        keyword: self.functions.symbols().resolve(arg.name).clone(),
+                    }
+                    .at(location.clone()),
+                );
+            }
+        }
+    }
+
     pub fn sort_constraints(&mut self) {
-        self.constraints
-            .sort_by_key(|&Constraint(ref lft, ref rgt, _)| {
-                vec![lft, rgt].into_iter().fold(0, |count, ty| {
-                    count + ty.free_vars().map(|vars| vars.len()).unwrap_or(0)
-                })
+        self.constraints.sort_by_key(|Constraint(lft, rgt, _)| {
+            vec![lft, rgt].into_iter().fold(0, |count, ty| {
+                count + ty.free_vars().map(|vars| vars.len()).unwrap_or(0)
             })
+        })
     }
 }
 
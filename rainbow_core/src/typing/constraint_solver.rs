@@ -1,40 +1,157 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use super::constraint_generator::Constraint;
+use super::constraint_generator::{Constraint, DeferredCheck};
 use super::substitution::{Subst, Substitutable};
 use super::type_errors::*;
 use super::types::*;
 
 use crate::frontend::NodeData;
+use crate::suggest::did_you_mean;
 
-pub fn solve(constraints: Vec<Constraint>, errors: &mut Vec<TypeError>) -> Subst {
-    let mut subst = HashMap::new();
+/// Solves constraints as they are generated, keeping one substitution for all of them, so that
+/// what is known so far can be looked at before every constraint has been generated.
+pub struct Solver {
+    subst: Subst,
+}
 
-    if constraints.len() == 0 {
-        return subst;
+impl Solver {
+    pub fn new() -> Solver {
+        Solver {
+            subst: HashMap::new(),
+        }
     }
 
-    dbg!("\n\nstarting unification\n\n");
-
-    let mut type_path: Vec<TypeLoc> = Vec::with_capacity(8);
-    for Constraint(left, right, location) in constraints {
-        let u = Unifier {
-            errors: errors,
-            subst: &mut subst,
-            path: &mut type_path,
-            left: &left,
-            right: &right,
-            location: &location,
-        };
-        u.unify();
+    /// Unify `constraints` on top of the ones solved before them.
+    pub fn solve(&mut self, constraints: Vec<Constraint>, errors: &mut Vec<TypeError>) {
+        if constraints.is_empty() {
+            return;
+        }
+
+        dbg!("\n\nstarting unification\n\n");
+
+        let mut type_path: Vec<TypeLoc> = Vec::with_capacity(8);
+        for Constraint(left, right, location) in constraints {
+            let u = Unifier {
+                errors,
+                subst: &mut self.subst,
+                path: &mut type_path,
+                left: &left,
+                right: &right,
+                location: &location,
+            };
+            u.unify();
+        }
+
+        dbg!("\n\nafter unification:");
+        for (ref name, ref ty) in self.subst.iter() {
+            dbg!("  {} = {}", name, ty);
+        }
     }
 
-    dbg!("\n\nafter unification:");
-    for (ref name, ref ty) in subst.iter() {
-        dbg!("  {} = {}", name, ty);
+    /// What `ty` is known to be from the constraints solved so far.
+    pub fn resolve(&self, ty: &Type) -> Type {
+        let mut ty = ty.apply_substitution(&self.subst);
+        // every pass replaces one more variable in a chain of them, if it replaces anything
+        for _ in 0..self.subst.len() {
+            let next = ty.apply_substitution(&self.subst);
+            if next == ty {
+                break;
+            }
+            ty = next;
+        }
+        ty
+    }
+
+    /// The type variables in `vars`, and every one they are bound to so far, however indirectly.
+    pub fn reachable_vars(&self, vars: HashSet<String>) -> HashSet<String> {
+        let mut reached = HashSet::with_capacity(vars.len());
+        let mut pending: Vec<String> = vars.into_iter().collect();
+        while let Some(var) = pending.pop() {
+            if let Some(ty) = self.subst.get(&var) {
+                if !reached.contains(&var) {
+                    pending.extend(ty.free_vars().unwrap_or_default());
+                }
+            }
+            reached.insert(var);
+        }
+        reached
     }
 
-    minimize_substitution(subst).unwrap()
+    /// The substitution that solves every constraint, once they have all been solved.
+    pub fn finish(self) -> Subst {
+        minimize_substitution(self.subst).unwrap()
+    }
+}
+
+/// Run the checks that had to wait for `subst` to be known.
+pub fn check_deferred(checks: Vec<DeferredCheck>, subst: &mut Subst, errors: &mut Vec<TypeError>) {
+    for check in checks {
+        match check {
+            DeferredCheck::RequiredPath {
+                root,
+                path,
+                location,
+            } => {
+                let mut ty = root.apply_substitution(subst);
+                for (i, name) in path.iter().enumerate() {
+                    let field = match ty {
+                        Type::Record(_, ref fields) => fields.get(name).cloned(),
+                        _ => None,
+                    };
+                    match field {
+                        Some(field) => {
+                            if field.optional() {
+                                let field_path = path[..i + 1].join(".");
+                                errors.push(
+                                    Problem::Unguarded(Partial::OptionalField(field_path))
+                                        .at(location.clone()),
+                                );
+                                break;
+                            }
+                            ty = field.get_type().apply_substitution(subst);
+                        }
+                        None => break,
+                    }
+                }
+            }
+            DeferredCheck::Interpolated { ty, location } => match ty.apply_substitution(subst) {
+                Type::Str | Type::Enum(_) | Type::Num | Type::Bool | Type::Any => {}
+                // nothing else says what it is, so it has to be something that can be printed
+                Type::Var(name) => {
+                    let default: Subst = Some((name, Type::Str)).into_iter().collect();
+                    for ty in subst.values_mut() {
+                        *ty = ty.apply_substitution(&default);
+                    }
+                    subst.extend(default);
+                }
+                other => errors.push(Problem::NotInterpolable(other).at(location)),
+            },
+            DeferredCheck::Literal {
+                expected,
+                value,
+                location,
+            } => {
+                let not_allowed =
+                    expected
+                        .iter()
+                        .find_map(|ty| match ty.apply_substitution(subst) {
+                            Type::Enum(allowed) if !allowed.contains(&value) => Some(allowed),
+                            _ => None,
+                        });
+                if let Some(allowed) = not_allowed {
+                    let suggestion = did_you_mean(&value, allowed.iter().map(String::as_str));
+                    errors.push(
+                        Problem::NotOneOf {
+                            value,
+                            allowed,
+                            suggestion,
+                        }
+                        .at(location),
+                    );
+                }
+            }
+        }
+    }
 }
 
 struct Unifier<'path, 'constraint, 'errs> {
@@ -56,8 +173,8 @@ impl<'p, 'c, 'e> Unifier<'p, 'c, 'e> {
         self.path.push(loc);
         let ty = {
             let child = Unifier {
-                left: left,
-                right: right,
+                left,
+                right,
                 path: self.path,
                 location: self.location,
                 errors: self.errors,
@@ -69,6 +186,27 @@ impl<'p, 'c, 'e> Unifier<'p, 'c, 'e> {
         ty
     }
 
+    /// Is the constraint on a literal, rather than a term whose value is only known at runtime?
+    fn is_literal(&self) -> bool {
+        match self.location.node_type {
+            crate::frontend::NodeType::Primitive(_) => self.path.is_empty(),
+            _ => false,
+        }
+    }
+
+    /// Unify types inside the ones being unified, at the same place in them.
+    fn nested(&mut self, left: &Type, right: &Type) -> Type {
+        Unifier {
+            left,
+            right,
+            path: self.path,
+            location: self.location,
+            errors: self.errors,
+            subst: self.subst,
+        }
+        .unify()
+    }
+
     fn unify(mut self) -> Type {
         use crate::Type::*;
         let left = self.left.apply_substitution(self.subst);
@@ -92,6 +230,101 @@ impl<'p, 'c, 'e> Unifier<'p, 'c, 'e> {
             (List(left_el), List(right_el)) => {
                 self.recur(TypeLoc::ListElement, &left_el, &right_el)
             }
+            (Enum(found), Enum(expected)) => {
+                if !found.iter().all(|value| expected.contains(value)) {
+                    self.add_problem(ConstraintProblem::Incompatible(
+                        Enum(found),
+                        Enum(expected.clone()),
+                    ));
+                }
+                Enum(expected)
+            }
+            // any of the values of an enum is a string
+            (Enum(_), Str) => Str,
+            // a string literal is checked against the values once every constraint is solved
+            (Str, Enum(expected)) if self.is_literal() => Enum(expected),
+            (Optional(left_inner), Optional(right_inner)) => {
+                let inner = self.nested(&left_inner, &right_inner);
+                Optional(Box::new(inner))
+            }
+            // a value that is there can be used where one might be absent, but not the other way
+            (found, Optional(expected)) if found != Any => {
+                let inner = self.nested(&found, &expected);
+                self.rebind(Optional(Box::new(inner)))
+            }
+            (Union(left_cases), Union(right_cases)) => {
+                let same_tags = left_cases.len() == right_cases.len()
+                    && left_cases.keys().all(|tag| right_cases.contains_key(tag));
+                if !same_tags {
+                    self.add_problem(ConstraintProblem::Incompatible(
+                        Union(left_cases.clone()),
+                        Union(right_cases),
+                    ));
+                    return Union(left_cases);
+                }
+                let mut cases = HashMap::with_capacity(left_cases.len());
+                for (tag, left_payload) in left_cases {
+                    let payload = match (left_payload, &right_cases[&tag]) {
+                        (Some(left), Some(right)) => {
+                            Some(self.recur(TypeLoc::Case(tag.clone()), &left, right))
+                        }
+                        (None, None) => None,
+                        (left, right) => {
+                            self.add_problem(ConstraintProblem::PayloadMismatch(tag.clone()));
+                            left.or_else(|| right.clone())
+                        }
+                    };
+                    cases.insert(tag, payload);
+                }
+                Union(cases)
+            }
+            // the fields of an update that it didn't set come from its source, which gains any
+            // of them it didn't have yet
+            (Update(source, overrides), Record(partial, fields)) => {
+                let mut rest = HashMap::with_capacity(fields.len());
+                for (name, field) in fields {
+                    match overrides.get(&name) {
+                        Some(found) => {
+                            self.recur(TypeLoc::Field(name), found.get_type(), field.get_type());
+                        }
+                        None => {
+                            rest.insert(name, field);
+                        }
+                    }
+                }
+                self.nested(&Var(source.clone()), &Record(partial, rest));
+                Update(source, overrides).apply_substitution(self.subst)
+            }
+            (Record(partial, mut fields), Update(source, overrides)) => {
+                for (name, expected) in &overrides {
+                    match fields.remove(name) {
+                        Some(found) => {
+                            let loc = TypeLoc::Field(name.clone());
+                            self.recur(loc, found.get_type(), expected.get_type());
+                        }
+                        None if !partial => {
+                            let names = fields.keys().map(String::as_str);
+                            self.add_problem(ConstraintProblem::FieldMissing {
+                                suggestion: did_you_mean(name, names),
+                                name: name.clone(),
+                            });
+                        }
+                        None => {}
+                    }
+                }
+                self.nested(&Record(partial, fields), &Var(source.clone()));
+                Update(source, overrides).apply_substitution(self.subst)
+            }
+            (Update(left_source, left_overrides), Update(right_source, right_overrides)) => {
+                for (name, expected) in &right_overrides {
+                    if let Some(found) = left_overrides.get(name) {
+                        let loc = TypeLoc::Field(name.clone());
+                        self.recur(loc, found.get_type(), expected.get_type());
+                    }
+                }
+                self.nested(&Var(left_source.clone()), &Var(right_source));
+                Update(left_source, left_overrides).apply_substitution(self.subst)
+            }
             (Record(left_partial, left_fields), Record(right_partial, mut right_fields)) => {
                 let mut fields = HashMap::new();
 
@@ -99,20 +332,23 @@ impl<'p, 'c, 'e> Unifier<'p, 'c, 'e> {
                 dbg!("  left: {}", Record(left_partial, left_fields.clone()));
                 dbg!("  right: {}", Record(right_partial, right_fields.clone()));
 
+                // names of the fields that were found, for suggestions when one is missing
+                let left_names: Vec<String> = left_fields.keys().cloned().collect();
+
                 for (name, left_field) in left_fields {
                     match right_fields.remove(&name) {
+                        // a record with more fields than expected can be used all the same, and
+                        // keeps them for whatever else it is used as
                         None => {
-                            if left_field.required() && !right_partial {
-                                self.add_problem(ConstraintProblem::FieldMissing(name.clone()));
-                            } else {
-                                fields.insert(
-                                    name,
-                                    left_field.map_type(|ty| ty.apply_substitution(self.subst)),
-                                );
-                            }
+                            fields.insert(
+                                name,
+                                left_field.map_type(|ty| ty.apply_substitution(self.subst)),
+                            );
                         }
                         Some(ref right_field) => {
-                            if left_field.required() && right_field.optional() {
+                            // reading a field that might be missing is checked once everything is
+                            // solved, so only closed records are checked here
+                            if left_field.optional() && right_field.required() && !right_partial {
                                 self.add_problem(ConstraintProblem::FieldOptional(name.clone()));
                             }
                             let new_ty = {
@@ -128,13 +364,19 @@ impl<'p, 'c, 'e> Unifier<'p, 'c, 'e> {
                 }
 
                 // right_fields now only contains fields that were *not* in left_fields
-                // if left was a partial type, we extend it with the fields from right.
-                if left_partial {
-                    for (name, right_field) in right_fields {
+                // if left was a partial type, we extend it with the fields from right,
+                // otherwise any required field in right is missing from left.
+                for (name, right_field) in right_fields {
+                    if left_partial {
                         fields.insert(
                             name,
                             right_field.map_type(|ty| ty.apply_substitution(self.subst)),
                         );
+                    } else if right_field.required() {
+                        self.add_problem(ConstraintProblem::FieldMissing {
+                            suggestion: did_you_mean(&name, left_names.iter().map(String::as_str)),
+                            name,
+                        });
                     }
                 }
 
@@ -150,8 +392,12 @@ impl<'p, 'c, 'e> Unifier<'p, 'c, 'e> {
                         actual: left_in.len(),
                     });
                 }
+                // unify the inputs as they were given, before substitution, so that a variable
+                // standing for one is bound to what both sides know about it
+                let left_in = block_inputs(self.left).unwrap_or(left_in);
+                let right_in = block_inputs(self.right).unwrap_or(right_in);
                 let mut inputs: Vec<Type> = Vec::with_capacity(right_in.len());
-                for (i, (e_in, a_in)) in left_in.into_iter().zip(right_in.into_iter()).enumerate() {
+                for (i, (e_in, a_in)) in left_in.into_iter().zip(right_in).enumerate() {
                     let in_ty = self.recur(TypeLoc::BlockArg(i), &a_in, &e_in); // <== block input variance is inverted
                     inputs.push(in_ty);
                 }
@@ -160,6 +406,26 @@ impl<'p, 'c, 'e> Unifier<'p, 'c, 'e> {
                     Box::new(self.recur(TypeLoc::BlockBody, &left_out, &right_out)),
                 )
             }
+            (Money(left_currency), Money(right_currency)) => {
+                match (*left_currency, *right_currency) {
+                    // an amount in any currency can't be used where a particular one is expected
+                    (Any, expected) => {
+                        self.add_problem(ConstraintProblem::UnknownCurrency);
+                        Money(Box::new(expected))
+                    }
+                    (found, Any) => Money(Box::new(found)),
+                    (left_currency, right_currency) => {
+                        Money(Box::new(self.nested(&left_currency, &right_currency)))
+                    }
+                }
+            }
+            (Currency(left_code), Currency(right_code)) => {
+                self.add_problem(ConstraintProblem::Incompatible(
+                    Type::money_in(&left_code),
+                    Type::money_in(&right_code),
+                ));
+                Currency(left_code)
+            }
             (Any, _) => Any,
             (_, Any) => Any,
             (left, right) => {
@@ -215,6 +481,14 @@ impl<'p, 'c, 'e> Unifier<'p, 'c, 'e> {
     }
 }
 
+/// The inputs of `ty`, if it is a block.
+fn block_inputs(ty: &Type) -> Option<Vec<Type>> {
+    match *ty {
+        Type::Block(ref inputs, _) => Some(inputs.clone()),
+        _ => None,
+    }
+}
+
 /// Repeatedly replace any `var1 = var2` binding in `subst` with `var1 = subst.get(var2)`
 ///
 /// This _should_ replace all type variables as long as there is some concrete type for var2
@@ -271,6 +545,12 @@ fn minimize_substitution(mut subst: Subst) -> Result<Subst, String> {
         }
     }
 
+    // updates of records that have been closed since are records themselves
+    let closed = subst.clone();
+    for ty in subst.values_mut() {
+        *ty = ty.apply_substitution(&closed);
+    }
+
     #[cfg(test)]
     {
         dbg!("");
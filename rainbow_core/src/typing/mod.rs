@@ -22,9 +22,10 @@
 //!
 //! Lists are homogeneously typed. A list type `Left` is satisfied by another list
 //! type `Right` iff the element type of `Left` is satisfied by the element type
-//! of `Right`.
+//! of `Right`. The type of a list literal is the narrowest type all of its items
+//! satisfy, so `[ [a=1] [a=2 b=3] ]` is a `[ [ a=number b?=number ]... ]`.
 //!
-//! The type of a list containing elements of type `E` is written `[ E ]`
+//! The type of a list containing elements of type `E` is written `[ E... ]`
 //!
 //! ### Records
 //!
@@ -33,10 +34,48 @@
 //!
 //! A record type `Left` is satisfied by another record type `Right` if every
 //! non-optional field in `Left` is present (and non-optional) in `Right` and has
-//! the same type.
+//! the same type. `Right` may have other fields too, so a record can be passed
+//! to a function that only needs some of its fields.
 //!
 //! The type of a record with a required field `foo` of type `F` and optional
-//! field `bar` of type `B` is written `[ foo = F bar = B? ]`.
+//! field `bar` of type `B` is written `[ foo=F bar?=B ]`.
+//!
+//! ### Optional values
+//!
+//! An optional type `Left` is satisfied by another optional type `Right` iff
+//! the inner type of `Left` is satisfied by the inner type of `Right`, and by
+//! any type that satisfies its inner type. An optional value can't be used
+//! where a value is required until it's handled with `withDefault:` or
+//! `ifPresent:`.
+//!
+//! The type of a value of type `T` that may be absent is written `T?`.
+//!
+//! ### Unions
+//!
+//! A union type `Left` is satisfied by another union type `Right` iff both have
+//! the same cases, and the payload of each case in `Left` is satisfied by the
+//! payload of the same case in `Right`. `match:` must have a block for every
+//! case.
+//!
+//! The type of a union with a case `pending` and a case `refunded` with a
+//! payload of type `R` is written `< pending | refunded(R) >`.
+//!
+//! ### Enums
+//!
+//! An enum type `Left` is satisfied by another enum type `Right` iff every
+//! value of `Right` is a value of `Left`. Every enum satisfies the string
+//! type, and a string literal satisfies an enum that has it as a value.
+//!
+//! The type of a string that can be `"pending"` or `"paid"` is written
+//! `< "pending" | "paid" >`.
+//!
+//! ### Opaque types
+//!
+//! An opaque type `Left` is satisfied only by an opaque type `Right` with the
+//! same name. Scripts can pass host values of an opaque type around, but not
+//! inspect them.
+//!
+//! The type of a host value named `CustomerId` is written `opaque<CustomerId>`.
 //!
 //! ### Blocks
 //!
@@ -45,7 +84,7 @@
 //!
 //! 1. `Right` expects _at most_ as many inputs as `Left`.
 //! 2. Each input type of `Left` is satisfied by corresponding input type of
-//! `Right`.
+//!    `Right`.
 //! 3. The output type of `Left` is satisfied by the output type of `Right`.
 //!
 //! The type of a block taking arguments of types `A` and `B` and returns a value
@@ -69,23 +108,32 @@
 //! documentation. The type of a function named `foo` taking a `foo` argument of
 //! type `F`, a variadic number of arguments named `bar` of type `B`, an optional
 //! argument `baz` of type `Z`,  and returning type `C` would therefore be written
-//! `foo:F [bar]:B baz:?Z => C`.
+//! `foo: F [bar]?: B baz?: Z :: C`. A `!` after the function name marks a partial
+//! function, and after any other keyword an argument that makes the call partial,
+//! like `calc: number [dividedBy]?!: number :: number`.
 //!
 //! A more useful example is the type of `if`, which is written as follows:
 //!
 //! ```rainbow,ignore
-//! {
-//!     if: boolean
-//!     [and]: { boolean }
-//!     [or]: { boolean }
-//!     then: { A }
-//!     else: { A }
-//! }
+//! if: boolean
+//! [and]?: { boolean }
+//! [or]?: { boolean }
+//! then: { A }
+//! else: { A }
+//! :: A
 //! ```
 //!
+//! ### Partial functions
+//!
+//! Functions that may fail to produce a value for some inputs are _partial_.
+//! Every call to a partial function (or a total function given an argument
+//! that makes it partial, like `calc: ... dividedBy:`) must appear inside the
+//! first argument of `try: ... or: ...`. The same applies to variable paths
+//! that read an optional record field.
 //!
 mod constraint_generator;
 mod constraint_solver;
+mod notation;
 mod substitution;
 mod type_env;
 mod type_errors;
@@ -125,23 +173,13 @@ where
     G: IntoIterator<Item = (String, Type)>,
 {
     use self::constraint_generator::generate;
-    use self::constraint_solver::solve;
+    use self::constraint_solver::check_deferred;
     use self::substitution::Substitutable;
     use self::type_env::TypeEnv;
 
     let mut initial_env: TypeEnv = globals.into_iter().collect();
-    let (inferred_type, constraints, mut errors) = generate(ns, &mut initial_env, tree);
-
-    #[cfg(test)]
-    {
-        use self::constraint_generator::Constraint;
-        dbg!("constraints:");
-        for &Constraint(ref lft, ref rgt, _) in constraints.iter() {
-            dbg!("  {} ~ {}", lft, rgt);
-        }
-    }
-
-    let subst = solve(constraints, &mut errors);
+    let (inferred_type, mut subst, deferred, mut errors) = generate(ns, &mut initial_env, tree);
+    check_deferred(deferred, &mut subst, &mut errors);
 
     let mut inferred_globals: HashMap<_, Type> = initial_env.apply_substitution(&subst).into();
     inferred_globals.retain(|k, _v| initial_env.contains_key(k));
@@ -149,6 +187,6 @@ where
     TypeCheckerResult {
         inputs: inferred_globals,
         output: inferred_type.apply_substitution(&subst),
-        errors: errors,
+        errors,
     }
 }
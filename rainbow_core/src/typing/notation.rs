@@ -0,0 +1,448 @@
+//! Parsing types from the notation they are displayed in, so that hosts can declare the types of
+//! inputs as text.
+//!
+//! ```text
+//! type   = (name | money | opaque | list | record | block | union | enum) "?"?
+//! money  = "money" ("<" name ">")?
+//! opaque = "opaque" "<" name ">"
+//! list   = "[" type "..."? "]"
+//! record = "[" "?"? field* "?"? "]"
+//! field  = name "?"? "=" type
+//! block  = "{" (type* "=>")? type "}"
+//! union  = "<" case ("|" case)* ">"
+//! case   = name ("(" type ")")?
+//! enum   = "<" string ("|" string)* ">"
+//! ```
+//!
+//! A `?` right after a type, without any space between them, makes it optional. One after a space
+//! can only close a partial record.
+//!
+//! A name is one of the primitive types, or otherwise a type variable. The currency of money is a
+//! name of three capital letters like `USD`, or a type variable.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use super::types::{RecordField, Type};
+
+impl FromStr for Type {
+    type Err = String;
+
+    /// Parse a type like `[ name=string tags?=[ string... ] ]`, as written by `Display`.
+    fn from_str(text: &str) -> Result<Type, String> {
+        let mut parser = TypeParser {
+            tokens: tokenize(text)?,
+            position: 0,
+        };
+        let ty = parser.ty()?;
+        match parser.peek() {
+            None => Ok(ty),
+            Some(&(token, at)) => Err(format!("unexpected `{}` at {}", token, at)),
+        }
+    }
+}
+
+/// Split `text` into words and punctuation, along with the position of each.
+fn tokenize(text: &str) -> Result<Vec<(&str, usize)>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(|c: char| !c.is_whitespace()) {
+        rest = &rest[start..];
+        let at = text.len() - rest.len();
+        let len = if rest.starts_with("...") {
+            3
+        } else if rest.starts_with("=>") {
+            2
+        } else if rest.starts_with('"') {
+            match string_len(rest) {
+                Some(len) => len,
+                None => return Err(format!("unterminated string at {}", at)),
+            }
+        } else if rest.starts_with(|c: char| "[]{}<>()|=?".contains(c)) {
+            1
+        } else {
+            let len = rest.find(|c: char| !is_name_char(c)).unwrap_or(rest.len());
+            if len == 0 {
+                let c = rest.chars().next().unwrap();
+                return Err(format!("unexpected `{}` at {}", c, at));
+            }
+            len
+        };
+        tokens.push((&rest[..len], at));
+        rest = &rest[len..];
+    }
+    Ok(tokens)
+}
+
+/// The length of the string at the start of `text`, including both quotes, or `None` if it is
+/// never closed. Quotes escaped with a backslash don't end it.
+fn string_len(text: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (offset, c) in text.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Some(offset + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The value of a string written the way `Display` writes the values of an enum, with the escapes
+/// of `{:?}`, or `None` if it isn't one.
+fn unquote(token: &str) -> Option<String> {
+    let inner = token.strip_prefix('"')?.strip_suffix('"')?;
+    let mut value = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        value.push(match chars.next()? {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            'u' => {
+                let rest = chars.as_str().strip_prefix('{')?;
+                let end = rest.find('}')?;
+                let code = u32::from_str_radix(&rest[..end], 16).ok()?;
+                chars = rest[end + 1..].chars();
+                std::char::from_u32(code)?
+            }
+            c @ ('\\' | '"' | '\'') => c,
+            _ => return None,
+        });
+    }
+    Some(value)
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+fn is_name(token: &str) -> bool {
+    token.chars().all(is_name_char)
+}
+
+fn is_currency(token: &str) -> bool {
+    token.len() == 3 && token.chars().all(|c| c.is_ascii_uppercase())
+}
+
+struct TypeParser<'t> {
+    tokens: Vec<(&'t str, usize)>,
+    position: usize,
+}
+
+impl<'t> TypeParser<'t> {
+    fn peek(&self) -> Option<&(&'t str, usize)> {
+        self.tokens.get(self.position)
+    }
+
+    fn peek_is(&self, offset: usize, expected: &str) -> bool {
+        self.tokens
+            .get(self.position + offset)
+            .is_some_and(|&(token, _)| token == expected)
+    }
+
+    fn next(&mut self) -> Result<(&'t str, usize), String> {
+        let token = self
+            .peek()
+            .cloned()
+            .ok_or_else(|| String::from("unexpected end of type"))?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), String> {
+        match self.next()? {
+            (token, _) if token == expected => Ok(()),
+            (token, at) => Err(format!(
+                "expected `{}` at {}, not `{}`",
+                expected, at, token
+            )),
+        }
+    }
+
+    fn name(&mut self) -> Result<&'t str, String> {
+        match self.next()? {
+            (token, _) if is_name(token) => Ok(token),
+            (token, at) => Err(format!("expected a name at {}, not `{}`", at, token)),
+        }
+    }
+
+    fn ty(&mut self) -> Result<Type, String> {
+        let ty = self.required_ty()?;
+        let (last, at) = self.tokens[self.position - 1];
+        match self.peek() {
+            Some(&("?", question_at)) if question_at == at + last.len() => {
+                self.position += 1;
+                Ok(Type::optional(ty))
+            }
+            _ => Ok(ty),
+        }
+    }
+
+    fn required_ty(&mut self) -> Result<Type, String> {
+        match self.peek() {
+            Some(&("[", _)) => self.list_or_record(),
+            Some(&("{", _)) => self.block(),
+            Some(&("<", _)) => self.union(),
+            _ => {
+                let name = self.name()?;
+                Ok(match name {
+                    "any" => Type::Any,
+                    "never" => Type::Never,
+                    "number" => Type::Num,
+                    "string" => Type::Str,
+                    "boolean" => Type::Bool,
+                    "time" => Type::Time,
+                    "duration" => Type::Duration,
+                    "money" if self.peek_is(0, "<") => {
+                        self.expect("<")?;
+                        let currency = self.name()?;
+                        self.expect(">")?;
+                        if is_currency(currency) {
+                            Type::money_in(currency)
+                        } else {
+                            Type::Money(Box::new(Type::var(currency)))
+                        }
+                    }
+                    "money" => Type::money(),
+                    "opaque" => {
+                        self.expect("<")?;
+                        let name = self.name()?;
+                        self.expect(">")?;
+                        Type::Opaque(String::from(name))
+                    }
+                    name => Type::var(name),
+                })
+            }
+        }
+    }
+
+    fn list_or_record(&mut self) -> Result<Type, String> {
+        self.expect("[")?;
+        if self.peek_is(1, "with") {
+            let source = self.name()?;
+            self.position += 1;
+            let fields = self.fields()?;
+            self.expect("]")?;
+            return Ok(Type::Update(String::from(source), fields));
+        }
+        let is_record = self.peek_is(0, "?")
+            || self.peek_is(0, "]")
+            || self.peek_is(1, "=")
+            || (self.peek_is(1, "?") && self.peek_is(2, "="));
+        if !is_record {
+            let element = self.ty()?;
+            if self.peek_is(0, "...") {
+                self.position += 1;
+            }
+            self.expect("]")?;
+            return Ok(Type::list_of(element));
+        }
+
+        let partial = self.peek_is(0, "?");
+        if partial {
+            self.position += 1;
+        }
+        let fields = self.fields()?;
+        if partial {
+            self.expect("?")?;
+        }
+        self.expect("]")?;
+        Ok(Type::Record(partial, fields))
+    }
+
+    /// The fields of a record, up to the `]` or `?]` that ends it.
+    fn fields(&mut self) -> Result<HashMap<String, RecordField>, String> {
+        let mut fields = HashMap::new();
+        while !self.peek_is(0, "]") && !self.peek_is(0, "?") {
+            let (_, at) = *self.peek().ok_or("unexpected end of type")?;
+            let name = self.name()?;
+            let optional = self.peek_is(0, "?");
+            if optional {
+                self.position += 1;
+            }
+            self.expect("=")?;
+            let field = RecordField::new(self.ty()?, optional);
+            if fields.insert(String::from(name), field).is_some() {
+                return Err(format!("field `{}` at {} is given twice", name, at));
+            }
+        }
+        Ok(fields)
+    }
+
+    fn union(&mut self) -> Result<Type, String> {
+        self.expect("<")?;
+        if self
+            .peek()
+            .is_some_and(|&(token, _)| token.starts_with('"'))
+        {
+            return self.enumeration();
+        }
+        let mut cases = HashMap::new();
+        loop {
+            let (_, at) = *self.peek().ok_or("unexpected end of type")?;
+            let tag = self.name()?;
+            let payload = if self.peek_is(0, "(") {
+                self.position += 1;
+                let payload = self.ty()?;
+                self.expect(")")?;
+                Some(payload)
+            } else {
+                None
+            };
+            if cases.insert(String::from(tag), payload).is_some() {
+                return Err(format!("case `{}` at {} is given twice", tag, at));
+            }
+            if !self.peek_is(0, "|") {
+                break;
+            }
+            self.position += 1;
+        }
+        self.expect(">")?;
+        Ok(Type::Union(cases))
+    }
+
+    fn enumeration(&mut self) -> Result<Type, String> {
+        let mut values = Vec::new();
+        loop {
+            let (token, at) = self.next()?;
+            match unquote(token) {
+                Some(value) => values.push(value),
+                None => return Err(format!("expected a string at {}, not `{}`", at, token)),
+            }
+            if !self.peek_is(0, "|") {
+                break;
+            }
+            self.position += 1;
+        }
+        self.expect(">")?;
+        Ok(Type::Enum(values))
+    }
+
+    fn block(&mut self) -> Result<Type, String> {
+        self.expect("{")?;
+        let mut types = Vec::new();
+        while !self.peek_is(0, "}") && !self.peek_is(0, "=>") {
+            types.push(self.ty()?);
+        }
+        if self.peek_is(0, "=>") {
+            self.position += 1;
+            let output = self.ty()?;
+            self.expect("}")?;
+            return Ok(Type::block_from_to(types, output));
+        }
+        self.expect("}")?;
+        match types.len() {
+            1 => Ok(Type::quoted(types.remove(0))),
+            n => Err(format!(
+                "a block type without `=>` has one output type, not {}",
+                n
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trips_with_display() {
+        let types = vec![
+            Type::Num,
+            Type::var("$12"),
+            Type::money(),
+            Type::money_in("USD"),
+            Type::Money(Box::new(Type::var("C"))),
+            Type::list_of(Type::list_of(Type::Str)),
+            Type::list_of(Type::optional(Type::Num)),
+            Type::Enum(vec!["pending".into(), "paid".into()]),
+            Type::Enum(vec![
+                "say \"hi\"".into(),
+                "a\\b | c\n".into(),
+                "to:do".into(),
+            ]),
+            Type::list_of(Type::Opaque("CustomerId".into())),
+            Type::union_from_iter(vec![
+                ("pending", None),
+                ("refunded", Some(Type::Str)),
+                ("paid", Some(Type::optional(Type::money_in("EUR")))),
+            ]),
+            Type::Record(
+                true,
+                vec![(
+                    "a".to_string(),
+                    RecordField::new(Type::optional(Type::Str), true),
+                )]
+                .into_iter()
+                .collect(),
+            ),
+            Type::Record(false, HashMap::new()),
+            Type::Update(
+                "$3".into(),
+                vec![("a".to_string(), RecordField::new(Type::Num, false))]
+                    .into_iter()
+                    .collect(),
+            ),
+            Type::record_with_one_field("when", Type::Time, true),
+            Type::record_from_iter(vec![
+                ("name", Type::Str),
+                ("tags", Type::list_of(Type::Bool)),
+            ]),
+            Type::quoted(Type::Duration),
+            Type::block_from_to(
+                vec![
+                    Type::var("In"),
+                    Type::record_from_iter(vec![("a", Type::Any)]),
+                ],
+                Type::quoted(Type::Never),
+            ),
+        ];
+        for ty in types {
+            let text = ty.to_string();
+            assert_eq!(text.parse::<Type>(), Ok(ty), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_parses_loose_spacing() {
+        assert_eq!(
+            "[foo = number bar? = [string]]".parse::<Type>(),
+            "[ foo=number bar?=[ string... ] ]".parse::<Type>()
+        );
+        assert_eq!(
+            "{A B=>C}".parse::<Type>(),
+            Ok(Type::block_from_to(
+                vec![Type::var("A"), Type::var("B")],
+                Type::var("C")
+            ))
+        );
+    }
+
+    #[test]
+    fn test_reports_errors() {
+        assert_eq!(
+            "[ number".parse::<Type>(),
+            Err("unexpected end of type".into())
+        );
+        assert_eq!(
+            "{ A B }".parse::<Type>(),
+            Err("a block type without `=>` has one output type, not 2".into())
+        );
+        assert_eq!(
+            "[ a=number a=string ]".parse::<Type>(),
+            Err("field `a` at 11 is given twice".into())
+        );
+        assert_eq!(
+            "number ]".parse::<Type>(),
+            Err("unexpected `]` at 7".into())
+        );
+        assert_eq!("number!".parse::<Type>(), Err("unexpected `!` at 6".into()));
+    }
+}
@@ -1,8 +1,10 @@
 #[cfg(test)]
 use std::iter::empty;
 
+use std::collections::HashMap;
+
 use crate::typing;
-use crate::typing::Type;
+use crate::typing::{ConstraintProblem, Partial, Problem, RecordField, Type};
 
 use crate::test_helpers::*;
 
@@ -123,3 +125,635 @@ fn bigger_example() {
         ]))
     );
 }
+
+#[test]
+fn partial_functions_outside_try() {
+    let result = type_of("calc: 1 dividedBy: x", vec![]);
+    assert_eq!(
+        result
+            .errors
+            .iter()
+            .map(|e| e.problem())
+            .collect::<Vec<_>>(),
+        vec![&Problem::Unguarded(Partial::Argument {
+            function: "calc".into(),
+            keyword: "dividedBy".into(),
+        })]
+    );
+    assert_eq!(
+        type_of("try: { calc: 1 dividedBy: x } or: 0", vec![])
+            .unwrap()
+            .0,
+        Type::Num
+    );
+    assert_eq!(type_of("calc: 1 plus: x", vec![]).unwrap().0, Type::Num);
+
+    let result = type_of("try: 1 or: crash: \"nope\"", vec![]);
+    assert_eq!(
+        result
+            .errors
+            .iter()
+            .map(|e| e.problem())
+            .collect::<Vec<_>>(),
+        vec![&Problem::Unguarded(Partial::Function("crash".into()))]
+    );
+}
+
+#[test]
+fn optional_fields_outside_try() {
+    let mut fields = HashMap::new();
+    fields.insert("bar".to_string(), RecordField::new(Type::Num, true));
+    let globals = vec![("foo".to_string(), Type::record_from_map(fields))];
+
+    let result = type_of("calc: foo.bar plus: 1", globals.clone());
+    assert_eq!(
+        result
+            .errors
+            .iter()
+            .map(|e| e.problem())
+            .collect::<Vec<_>>(),
+        vec![&Problem::Unguarded(Partial::OptionalField("bar".into()))]
+    );
+    assert_eq!(
+        type_of("try: { calc: foo.bar plus: 1 } or: 0", globals)
+            .unwrap()
+            .0,
+        Type::Num
+    );
+}
+
+#[test]
+fn interpolated_terms_must_be_printable() {
+    assert_eq!(
+        type_of("\"{x} has {calc: n plus: 1} items\"", vec![])
+            .unwrap()
+            .0,
+        Type::Str
+    );
+    // an input that is only interpolated has to be a string
+    let (_, inputs) = type_of("\"{x}\"", vec![]).unwrap();
+    assert_eq!(inputs["x"], Type::Str);
+
+    let result = type_of("\"items: {[1 2]}\"", vec![]);
+    assert_eq!(
+        result
+            .errors
+            .iter()
+            .map(|e| e.problem())
+            .collect::<Vec<_>>(),
+        vec![&Problem::NotInterpolable(Type::list_of(Type::Num))]
+    );
+}
+
+#[test]
+fn times_and_durations() {
+    assert_eq!(
+        type_of("calcTime: 2024-03-15 plus: P1D", vec![]).unwrap().0,
+        Type::Time
+    );
+    assert_eq!(
+        type_of("durationFrom: start to: 2024-03-15", vec![])
+            .unwrap()
+            .1
+            .get("start"),
+        Some(&Type::Time)
+    );
+    assert!(!type_of("calcTime: 2024-03-15 plus: 1", vec![])
+        .errors
+        .is_empty());
+
+    let result = type_of("calcTime: 2024-03-15 subtract: PT1H", vec![]);
+    assert_eq!(
+        result
+            .errors
+            .iter()
+            .map(|e| e.problem())
+            .collect::<Vec<_>>(),
+        vec![&Problem::Unguarded(Partial::Argument {
+            function: "calcTime".into(),
+            keyword: "subtract".into(),
+        })]
+    );
+}
+
+#[test]
+fn money_in_one_currency() {
+    assert_eq!(
+        type_of("calcMoney: 12.50 USD plus: 1 USD times: 2", vec![])
+            .unwrap()
+            .0,
+        Type::money_in("USD")
+    );
+    assert_eq!(
+        type_of("calcMoney: price plus: 1 EUR", vec![])
+            .unwrap()
+            .1
+            .get("price"),
+        Some(&Type::money_in("EUR"))
+    );
+
+    let mixed = type_of("calcMoney: 12.50 USD plus: 1 EUR", vec![]);
+    assert_eq!(mixed.errors.len(), 1);
+    assert_eq!(
+        format!("{}", mixed.errors[0]),
+        "expected an amount in USD, but found an amount in EUR"
+    );
+
+    let globals = vec![("total".to_string(), Type::money())];
+    let unchecked = type_of("calcMoney: total plus: 1 USD", globals.clone());
+    assert_eq!(
+        unchecked
+            .errors
+            .iter()
+            .map(|e| e.problem())
+            .collect::<Vec<_>>(),
+        vec![&Problem::Constraint(
+            vec![],
+            ConstraintProblem::UnknownCurrency
+        )]
+    );
+    assert_eq!(
+        type_of(
+            "try: { checkCurrency: total like: 0 USD } or: 0 USD",
+            globals
+        )
+        .unwrap()
+        .0,
+        Type::money_in("USD")
+    );
+}
+
+#[test]
+fn declared_globals_are_checked() {
+    let globals = vec![(
+        "foo".to_string(),
+        Type::record_from_iter(vec![("bar", Type::Num)]),
+    )];
+    assert!(!type_of("upperCase: foo.bar", globals.clone())
+        .errors
+        .is_empty());
+    assert!(!type_of("foo.baz", globals.clone()).errors.is_empty());
+    assert_eq!(type_of("foo.bar", globals).unwrap().0, Type::Num);
+}
+
+#[test]
+fn suggestions_for_unknown_names() {
+    let problems = |code: &str, globals: Vec<(String, Type)>| -> Vec<Problem> {
+        type_of(code, globals)
+            .errors
+            .into_iter()
+            .map(|err| err.problem().clone())
+            .collect()
+    };
+
+    assert_eq!(
+        problems("countfrom: 1 to: 3", vec![]),
+        vec![Problem::UnknownFunction {
+            name: "countfrom".into(),
+            suggestion: Some("countFrom".into()),
+        }]
+    );
+    assert_eq!(
+        problems("calc: 4 dividedby: 2", vec![]),
+        vec![Problem::UnknownKeyword {
+            function: "calc".into(),
+            keyword: "dividedby".into(),
+            suggestion: Some("dividedBy".into()),
+        }]
+    );
+
+    let globals = vec![(
+        "order".to_string(),
+        Type::record_from_iter(vec![("cost", Type::Num), ("quantity", Type::Num)]),
+    )];
+    assert_eq!(
+        problems("calc: order.cots plus: 1", globals),
+        vec![Problem::Constraint(
+            vec![],
+            ConstraintProblem::FieldMissing {
+                name: "cots".into(),
+                suggestion: Some("cost".into()),
+            }
+        )]
+    );
+}
+
+#[test]
+fn argument_checks_against_signature() {
+    let problems = |code: &str| -> Vec<Problem> {
+        type_of(code, vec![])
+            .errors
+            .into_iter()
+            .map(|err| err.problem().clone())
+            .collect()
+    };
+
+    assert_eq!(
+        problems("if: x then: 1"),
+        vec![Problem::MissingArgument {
+            function: "if".into(),
+            keyword: "else".into(),
+        }]
+    );
+    assert_eq!(
+        problems("if: x then: 1 else: 2 else: 3"),
+        vec![Problem::DuplicateArgument {
+            function: "if".into(),
+            keyword: "else".into(),
+        }]
+    );
+    assert_eq!(
+        problems("if: x else: 1 then: 2"),
+        vec![Problem::ArgumentOrder {
+            function: "if".into(),
+            keyword: "then".into(),
+            after: "else".into(),
+        }]
+    );
+
+    // variadic arguments may repeat and interleave, optional ones may come in any order
+    assert_eq!(
+        problems("if: x and: y or: z and: w then: 1 else: 2"),
+        vec![]
+    );
+    assert_eq!(problems("compare: 1 atMost: 3 biggerThan: 0"), vec![]);
+    assert_eq!(problems("countFrom: 1 by: 2 to: 9"), vec![]);
+}
+
+#[test]
+fn bindings_are_generalized() {
+    let code = "none = []
+counted = each: none do: { x => calc: x plus: 1 }
+[ a = counted b = each: none do: { x => upperCase: x } ]";
+    let (ty, inputs) = type_of(code, vec![]).unwrap();
+    assert_eq!(
+        ty,
+        "[ a=[ number... ] b=[ string... ] ]"
+            .parse::<Type>()
+            .unwrap()
+    );
+    assert!(inputs.is_empty());
+
+    // a binding that uses an input has the input's type, so it can't be both
+    let result = type_of(
+        "name = x\n[ a = calc: name plus: 1 b = upperCase: name ]",
+        vec![],
+    );
+    assert_eq!(result.errors.len(), 1);
+    assert_eq!(result.inputs.keys().collect::<Vec<_>>(), vec!["x"]);
+}
+
+#[test]
+fn bindings_only_see_earlier_bindings() {
+    let problems = |code: &str| {
+        type_of(code, vec![("input".to_string(), Type::Num)])
+            .errors
+            .into_iter()
+            .map(|err| err.problem().clone())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        problems("a = b\nb = 1\na"),
+        vec![Problem::UsedBeforeBinding("b".into())]
+    );
+    assert_eq!(
+        problems("a = calc: a plus: 1\na"),
+        vec![Problem::UsedBeforeBinding("a".into())]
+    );
+    assert_eq!(
+        problems("a = 1\na = 2\ninput"),
+        vec![Problem::AlreadyDefined("a".into())]
+    );
+    assert_eq!(
+        problems("input = 2\ninput"),
+        vec![Problem::AlreadyDefined("input".into())]
+    );
+}
+
+#[test]
+fn block_patterns_constrain_their_arguments() {
+    let code = "each: offices do: { [name = n address = [city = c]] => \"{upperCase: n} in {c}\" }";
+    let (ty, inputs) = type_of(code, vec![]).unwrap();
+    assert_eq!(ty, Type::list_of(Type::Str));
+    assert_eq!(
+        inputs["offices"],
+        "[ [ address=[ city=string ] name=string ]... ]"
+            .parse()
+            .unwrap()
+    );
+
+    let problems = |code: &str, globals: Vec<(String, Type)>| {
+        type_of(code, globals)
+            .errors
+            .into_iter()
+            .map(|err| err.problem().clone())
+            .collect::<Vec<_>>()
+    };
+    let rows = vec![(
+        "rows".to_string(),
+        "[ [ a=number b?=number ]... ]".parse::<Type>().unwrap(),
+    )];
+    match problems("each: rows do: { [c = x] => x }", rows).as_slice() {
+        [Problem::Constraint(_, ConstraintProblem::FieldMissing { name, .. })] => {
+            assert_eq!(name, "c")
+        }
+        other => panic!("expected `c` to be missing, not {:?}", other),
+    }
+    assert_eq!(
+        problems("each: [[1 2]] do: { [x y] => calc: x plus: y }", vec![]),
+        vec![Problem::Unguarded(Partial::ListPattern("[x y]".into()))]
+    );
+    assert_eq!(
+        problems("each: [[a = 1 b = 2]] do: { [a = x b = x] => x }", vec![]),
+        vec![Problem::AlreadyDefined("x".into())]
+    );
+    assert_eq!(
+        problems(
+            "try: { each: [[[a = 1] [b = 2]]] do: { [[a = x] [b = x]] => x } } or: []",
+            vec![]
+        ),
+        vec![Problem::AlreadyDefined("x".into())]
+    );
+    assert_eq!(
+        problems(
+            "try: { each: [[1 2]] do: { [x y] => calc: x plus: y } } or: []",
+            vec![]
+        ),
+        vec![]
+    );
+}
+
+#[test]
+fn updates_replace_and_add_fields() {
+    let order = vec![(
+        "order".to_string(),
+        "[ id=number status=number ]".parse::<Type>().unwrap(),
+    )];
+    assert_eq!(
+        type_of("[ order with status = \"paid\" note = \"\" ]", order)
+            .unwrap()
+            .0,
+        "[ id=number note=string status=string ]"
+            .parse::<Type>()
+            .unwrap()
+    );
+    assert_eq!(
+        type_of("[ [ a = 1 ] with b = true ]", vec![]).unwrap().0,
+        "[ a=number b=boolean ]".parse::<Type>().unwrap()
+    );
+
+    // nothing is known about the fields of an input, besides the ones that are set
+    let (ty, inputs) = type_of("[ x with a = 1 ]", vec![]).unwrap();
+    assert_eq!(ty, "[ a=number ]".parse::<Type>().unwrap());
+    assert_eq!(inputs["x"], "[ ]".parse::<Type>().unwrap());
+
+    let result = type_of("[ 5 with a = 1 ]", vec![]);
+    assert_eq!(
+        result
+            .errors
+            .iter()
+            .map(|err| format!("{}", err))
+            .collect::<Vec<_>>(),
+        vec!["only a record can be updated with `with`, but this is a number"]
+    );
+    assert_eq!(result.output, "[ a=number ]".parse::<Type>().unwrap());
+
+    // fields of the source found after the update are fields of the update too
+    let (ty, inputs) = type_of("y = [ x with a = 1 ]\n[ p = y.b q = x.b ]", vec![]).unwrap();
+    match (&ty, &inputs["x"]) {
+        (Type::Record(false, fields), Type::Record(false, x_fields)) => {
+            assert_eq!(fields["p"], fields["q"]);
+            assert_eq!(fields["p"], x_fields["b"]);
+        }
+        other => panic!("expected two records, not {:?}", other),
+    }
+    assert_eq!(
+        type_of("y = [ [ a = 1 ] with b = 2 ]\ny.c", vec![])
+            .errors
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn optional_values_must_be_handled() {
+    let maybe_num = vec![("x".to_string(), Type::optional(Type::Num))];
+    assert_eq!(
+        type_of("withDefault: x or: 0", maybe_num.clone())
+            .unwrap()
+            .0,
+        Type::Num
+    );
+    assert_eq!(
+        type_of(
+            "ifPresent: x then: { n => calc: n plus: 1 } else: 0",
+            maybe_num.clone()
+        )
+        .unwrap()
+        .0,
+        Type::Num
+    );
+    // a value that is always there can be given where one might be absent
+    assert_eq!(
+        type_of("withDefault: 1 or: 0", vec![]).unwrap().0,
+        Type::Num
+    );
+    // but not the other way around
+    match type_of("calc: x plus: 1", maybe_num.clone())
+        .errors
+        .iter()
+        .map(|err| err.problem())
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        [Problem::Constraint(_, ConstraintProblem::Incompatible(found, expected))] => {
+            assert_eq!((found, expected), (&Type::optional(Type::Num), &Type::Num))
+        }
+        other => panic!("expected an incompatible type, not {:?}", other),
+    }
+    assert_eq!(
+        type_of("withDefault: x or: \"none\"", maybe_num)
+            .errors
+            .len(),
+        1
+    );
+
+    // an input that is only ever handled as optional is inferred as one
+    let (_, inputs) = type_of("withDefault: y or: \"\"", vec![]).unwrap();
+    assert_eq!(inputs["y"], Type::optional(Type::Str));
+}
+
+#[test]
+fn match_covers_every_case() {
+    let status = vec![(
+        "status".to_string(),
+        "< paid(number) | pending | refunded(string) >"
+            .parse::<Type>()
+            .unwrap(),
+    )];
+    let problems = |code: &str| {
+        type_of(code, status.clone())
+            .errors
+            .into_iter()
+            .map(|err| err.problem().clone())
+            .collect::<Vec<_>>()
+    };
+
+    let code = "match: status on: [
+        pending = { \"waiting\" }
+        paid = { amount => \"paid {amount}\" }
+        refunded = { reason => reason }
+    ]";
+    assert_eq!(type_of(code, status.clone()).unwrap().0, Type::Str);
+
+    assert_eq!(
+        problems("match: status on: [ pending = { 1 } paid = { 2 } ]"),
+        vec![Problem::NonExhaustive(vec!["refunded".into()])]
+    );
+    assert_eq!(
+        problems(
+            "match: status on: [ pending = { 1 } paid = { 2 } refunded = { 3 } refundd = { 4 } ]"
+        ),
+        vec![Problem::UnknownCase {
+            name: "refundd".into(),
+            suggestion: Some("refunded".into())
+        }]
+    );
+    match problems("match: status on: [ pending = { p => 1 } paid = { 2 } refunded = { 3 } ]")
+        .as_slice()
+    {
+        [Problem::Constraint(_, ConstraintProblem::BlockArity { expected, actual })] => {
+            assert_eq!((*expected, *actual), (0, 1))
+        }
+        other => panic!("expected a block arity problem, not {:?}", other),
+    }
+    assert_eq!(
+        problems(
+            "match: status on: [ pending = { 1 } paid = { 2 } refunded = { r => calc: r plus: 1 } ]"
+        )
+        .len(),
+        1
+    );
+
+    // what is matched on may only turn out to be a union later
+    assert_eq!(
+        problems(
+            "m = match: x on: [ pending = { 1 } paid = { n => n } ]\ne = equal: x to: status\nm"
+        ),
+        vec![Problem::NonExhaustive(vec!["refunded".into()])]
+    );
+
+    // an input that is matched on is inferred as the union of its cases
+    let (ty, inputs) = type_of("match: x on: [ yes = { 1 } no = { n => n } ]", vec![]).unwrap();
+    assert_eq!(ty, Type::Num);
+    assert_eq!(inputs["x"], "< no(number) | yes >".parse::<Type>().unwrap());
+}
+
+#[test]
+fn literals_compared_with_enums_must_be_allowed() {
+    let status = vec![(
+        "status".to_string(),
+        Type::Enum(vec!["pending".into(), "paid".into(), "refunded".into()]),
+    )];
+    let problems = |code: &str| {
+        type_of(code, status.clone())
+            .errors
+            .into_iter()
+            .map(|err| err.problem().clone())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(problems("equal: status to: \"paid\""), vec![]);
+    assert_eq!(problems("equal: \"paid\" to: status"), vec![]);
+    for code in [
+        "if: { equal: status to: \"refundd\" } then: 1 else: 2",
+        "if: { equal: \"refundd\" to: status } then: 1 else: 2",
+    ]
+    .iter()
+    {
+        assert_eq!(
+            problems(code),
+            vec![Problem::NotOneOf {
+                value: "refundd".into(),
+                allowed: vec!["pending".into(), "paid".into(), "refunded".into()],
+                suggestion: Some("refunded".into()),
+            }]
+        );
+    }
+    // any value of an enum can be used as a string, but not the other way around
+    assert_eq!(
+        type_of("upperCase: status", status.clone()).unwrap().0,
+        Type::Str
+    );
+    assert_eq!(problems("\"status: {status}\""), vec![]);
+    assert_eq!(problems("equal: status to: upperCase: \"paid\"").len(), 1);
+}
+
+#[test]
+fn opaque_types_only_unify_with_themselves() {
+    let globals = vec![
+        ("customer".to_string(), Type::opaque("CustomerId")),
+        ("other".to_string(), Type::opaque("CustomerId")),
+        ("order".to_string(), Type::opaque("OrderId")),
+    ];
+    let problems = |code: &str| {
+        type_of(code, globals.clone())
+            .errors
+            .into_iter()
+            .map(|err| err.problem().clone())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(problems("equal: customer to: other"), vec![]);
+    assert_eq!(
+        type_of("[ customer ]", globals.clone()).unwrap().0,
+        Type::list_of(Type::opaque("CustomerId"))
+    );
+    match problems("equal: customer to: order").as_slice() {
+        [Problem::Constraint(_, ConstraintProblem::Incompatible(_, _))] => {}
+        other => panic!("expected an incompatible types problem, not {:?}", other),
+    }
+    assert_eq!(problems("equal: customer to: \"c-1\"").len(), 1);
+    assert_eq!(problems("upperCase: customer").len(), 1);
+    assert_eq!(problems("\"{customer}\"").len(), 1);
+}
+
+#[test]
+fn lists_of_records_with_different_fields() {
+    let expected: Type = "[ [ a=number b?=number ]... ]".parse().unwrap();
+    assert_eq!(
+        type_of("[ [a = 1] [a = 2 b = 3] ]", vec![]).unwrap().0,
+        expected
+    );
+    assert_eq!(
+        type_of("[ [a = 2 b = 3] [a = 1] ]", vec![]).unwrap().0,
+        expected
+    );
+    assert_eq!(
+        type_of("[ [ [a = 1] ] [ [b = \"x\"] ] ]", vec![])
+            .unwrap()
+            .0,
+        "[ [ [ a?=number b?=string ]... ]... ]".parse().unwrap()
+    );
+
+    // a field that only some of the items have has to be guarded when it is read
+    let result = type_of("each: [ [a = 1] [a = 2 b = 3] ] do: { r => r.b }", vec![]);
+    assert_eq!(
+        result
+            .errors
+            .iter()
+            .map(|e| e.problem())
+            .collect::<Vec<_>>(),
+        vec![&Problem::Unguarded(Partial::OptionalField("b".into()))]
+    );
+    assert!(type_of(
+        "try: { each: [ [a = 1] [a = 2 b = 3] ] do: { r => r.b } } or: []",
+        vec![]
+    )
+    .errors
+    .is_empty());
+
+    // items still can't be of unrelated types
+    assert_eq!(type_of("[ 1 \"a\" ]", vec![]).errors.len(), 1);
+    assert_eq!(type_of("[ [a = 1] [a = \"a\"] ]", vec![]).errors.len(), 1);
+}
@@ -2,6 +2,7 @@ use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 
+use super::constraint_solver::Solver;
 use super::substitution::*;
 use super::types::Type;
 use crate::scope::Scope;
@@ -40,6 +41,19 @@ impl TypeEnv {
         self.schemes.get(name).is_some()
     }
 
+    /// Has `name` been used without being defined, making it an input?
+    pub fn is_undefined(&self, name: &str) -> bool {
+        self.undefined.borrow().contains(name)
+    }
+
+    /// Define `name` as `ty`, generalized over every type variable that the rest of the
+    /// environment doesn't also use, as far as `solver` knows so far.
+    pub fn define_generalized(&mut self, name: String, ty: Type, solver: &Solver) {
+        let env_vars = solver.reachable_vars(self.free_vars().unwrap_or_default());
+        let scheme = Scheme::generalize(&env_vars, solver.resolve(&ty));
+        self.schemes.insert(name, scheme);
+    }
+
     /// get the scheme for the given name, or instantiate a new scheme with a var from `fresh_vars`.
     ///
     /// adds `name` to the `self.undefined` set if there was no pre-existing scheme.
@@ -77,9 +91,9 @@ impl From<Vec<(String, Type)>> for TypeEnv {
     }
 }
 
-impl Into<HashMap<String, Type>> for TypeEnv {
-    fn into(self) -> HashMap<String, Type> {
-        let schemes = self.schemes.flatten();
+impl From<TypeEnv> for HashMap<String, Type> {
+    fn from(env: TypeEnv) -> HashMap<String, Type> {
+        let schemes = env.schemes.flatten();
         HashMap::from_iter(
             schemes
                 .into_iter()
@@ -116,28 +130,20 @@ impl Scheme {
     fn new(ty: Type) -> Scheme {
         Scheme {
             vars: HashSet::new(),
-            ty: ty,
+            ty,
         }
     }
 
-    /// Generalize a type scheme by closing over all free type variables.
-    ///
-    /// Why this dead code is here: it was implemented in the "Write You a Haskell" code, but only
-    /// used for let bindings, which Rainbow doesn't have. I am leaving it here in case:
-    ///
-    ///   1. There's a bug in the rest of the code here and I've missed a place where I should be
-    ///      be generalizing a type scheme.
+    /// Generalize a type scheme by closing over all free type variables that aren't free in `env`.
     ///
-    ///   2. There is a need for an explicit `let` binding in Rainbow. (Currently the prelude defines a
-    ///      function `with: { ... } do: { x => ... }`, which serves much the same purpose).
-    #[allow(dead_code)]
-    fn generalize(env: &TypeEnv, ty: Type) -> Scheme {
-        let vars = match (ty.free_vars(), env.free_vars()) {
-            (None, _) => HashSet::new(),
-            (Some(lft), None) => lft,
-            (Some(lft), Some(rgt)) => lft.difference(&rgt).cloned().collect(),
+    /// This is only used for the bindings at the top of a script, so that a binding like
+    /// `identity = { x => x }` can be used with a different type each time.
+    fn generalize(env_vars: &HashSet<String>, ty: Type) -> Scheme {
+        let vars = match ty.free_vars() {
+            None => HashSet::new(),
+            Some(vars) => vars.difference(env_vars).cloned().collect(),
         };
-        Scheme { ty: ty, vars: vars }
+        Scheme { ty, vars }
     }
 
     /// Instantiate a scheme by creating fresh type variables for every variable in `self.vars` that
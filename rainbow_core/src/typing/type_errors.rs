@@ -1,3 +1,5 @@
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
 use super::types::Type;
 use crate::frontend::NodeData;
 
@@ -9,29 +11,111 @@ pub struct TypeError {
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Problem {
-    UnknownFunction,
-    UnknownKeyword(String),
+    UnknownFunction {
+        name: String,
+        suggestion: Option<String>,
+    },
+    UnknownKeyword {
+        function: String,
+        keyword: String,
+        suggestion: Option<String>,
+    },
+    /// A required argument was not given.
+    MissingArgument {
+        function: String,
+        keyword: String,
+    },
+    /// A non-variadic argument was given more than once.
+    DuplicateArgument {
+        function: String,
+        keyword: String,
+    },
+    /// An argument was given after a required argument that should follow it.
+    ArgumentOrder {
+        function: String,
+        keyword: String,
+        after: String,
+    },
     Constraint(Vec<TypeLoc>, ConstraintProblem),
+    /// Something that may fail at runtime was used outside of a `try: ... or: ...` block.
+    Unguarded(Partial),
+    /// A term interpolated into a string isn't a string, number or boolean.
+    NotInterpolable(Type),
+    /// The value before `with` in an update isn't a record.
+    NotUpdatable(Type),
+    /// A name was used before the line binding it, or in its own binding.
+    UsedBeforeBinding(String),
+    /// A name was bound that is already an input or an earlier binding.
+    AlreadyDefined(String),
+    /// A string literal given where only some strings are allowed, that isn't one of them.
+    NotOneOf {
+        value: String,
+        allowed: Vec<String>,
+        suggestion: Option<String>,
+    },
+    /// A `match:` without a case for every case of the union, the missing ones are given.
+    NonExhaustive(Vec<String>),
+    /// A `match:` with a case that the union doesn't have.
+    UnknownCase {
+        name: String,
+        suggestion: Option<String>,
+    },
+}
+
+impl TypeError {
+    pub fn location(&self) -> &NodeData {
+        &self.location
+    }
+
+    pub fn problem(&self) -> &Problem {
+        &self.error
+    }
 }
 
 impl Problem {
     pub fn at(self, location: NodeData) -> TypeError {
         TypeError {
-            location: location,
+            location,
             error: self,
         }
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Partial {
+    /// A call to a function that isn't total.
+    Function(String),
+    /// A keyword argument that makes an otherwise total function call partial.
+    Argument { function: String, keyword: String },
+    /// A variable path that reads an optional record field.
+    OptionalField(String),
+    /// A block argument that takes the first items of a list, which might not have that many.
+    ListPattern(String),
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ConstraintProblem {
-    AlreadyBound { name: String, old: Type, new: Type },
+    AlreadyBound {
+        name: String,
+        old: Type,
+        new: Type,
+    },
     InfiniteType(String, Type),
     RebindUndefined(String),
     Incompatible(Type, Type),
-    BlockArity { expected: usize, actual: usize },
-    FieldMissing(String),
+    BlockArity {
+        expected: usize,
+        actual: usize,
+    },
+    FieldMissing {
+        name: String,
+        suggestion: Option<String>,
+    },
     FieldOptional(String),
+    /// An amount whose currency is only known at runtime was used where a currency is expected.
+    UnknownCurrency,
+    /// A case of a union has a payload on one side only.
+    PayloadMismatch(String),
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -49,4 +133,272 @@ pub enum TypeLoc {
     Field(String),
     BlockArg(usize),
     BlockBody,
+    Case(String),
+}
+
+impl Display for TypeError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        use self::Problem::*;
+        match *self {
+            UnknownFunction {
+                ref name,
+                ref suggestion,
+            } => {
+                write!(f, "there is no function called `{}:`", name)?;
+                write_suggestion(f, suggestion, ":")
+            }
+            UnknownKeyword {
+                ref function,
+                ref keyword,
+                ref suggestion,
+            } => {
+                write!(
+                    f,
+                    "`{}:` doesn't take an argument called `{}:`",
+                    function, keyword
+                )?;
+                write_suggestion(f, suggestion, ":")
+            }
+            MissingArgument {
+                ref function,
+                ref keyword,
+            } => write!(f, "`{}:` is missing the `{}:` argument", function, keyword),
+            DuplicateArgument {
+                ref function,
+                ref keyword,
+            } => write!(
+                f,
+                "`{}:` can only be given one `{}:` argument",
+                function, keyword
+            ),
+            ArgumentOrder {
+                ref keyword,
+                ref after,
+                ..
+            } => write!(f, "`{}:` must come before `{}:`", keyword, after),
+            Constraint(ref path, ref problem) => {
+                write!(f, "{}", problem)?;
+                if !path.is_empty() {
+                    write!(f, " (in {})", describe_path(path))?;
+                }
+                Ok(())
+            }
+            Unguarded(ref partial) => write!(
+                f,
+                "{}, so it must be inside the first part of `try: ... or: ...`",
+                partial
+            ),
+            NotInterpolable(ref ty) => write!(
+                f,
+                "only strings, numbers and true or false can be put in a string, but this is {}",
+                describe_type(ty)
+            ),
+            NotUpdatable(ref ty) => write!(
+                f,
+                "only a record can be updated with `with`, but this is {}",
+                describe_type(ty)
+            ),
+            UsedBeforeBinding(ref name) => write!(
+                f,
+                "`{}` is used before it is bound, a name can only be used after its binding",
+                name
+            ),
+            AlreadyDefined(ref name) => write!(
+                f,
+                "`{}` is already defined, each name can only be bound once",
+                name
+            ),
+            NotOneOf {
+                ref value,
+                ref allowed,
+                ref suggestion,
+            } => {
+                write!(f, "{:?} is not one of {}", value, quote_all(allowed))?;
+                match *suggestion {
+                    Some(ref value) => write!(f, ", did you mean {:?}?", value),
+                    None => Ok(()),
+                }
+            }
+            NonExhaustive(ref missing) => write!(
+                f,
+                "`match:` needs a case for {}",
+                missing
+                    .iter()
+                    .map(|tag| format!("`{}`", tag))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            UnknownCase {
+                ref name,
+                ref suggestion,
+            } => {
+                write!(f, "`{}` is not one of the cases being matched", name)?;
+                write_suggestion(f, suggestion, "")
+            }
+        }
+    }
+}
+
+impl Display for Partial {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        use self::Partial::*;
+        match *self {
+            Function(ref name) => write!(f, "`{}:` can fail", name),
+            Argument {
+                ref function,
+                ref keyword,
+            } => write!(f, "`{}: ... {}:` can fail", function, keyword),
+            OptionalField(ref path) => write!(f, "`{}` might be missing", path),
+            ListPattern(ref pattern) => write!(f, "`{}` fails on shorter lists", pattern),
+        }
+    }
+}
+
+impl Display for ConstraintProblem {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        use self::ConstraintProblem::*;
+        match *self {
+            AlreadyBound {
+                ref old, ref new, ..
+            } => write!(
+                f,
+                "this is used as {} here, but as {} elsewhere",
+                describe_type(new),
+                describe_type(old)
+            ),
+            InfiniteType(_, _) => write!(f, "this value would have to contain itself"),
+            RebindUndefined(ref name) => write!(
+                f,
+                "internal type checker error (rebinding undefined type variable {})",
+                name
+            ),
+            Incompatible(ref found, ref expected) => write!(
+                f,
+                "expected {}, but found {}",
+                describe_type(expected),
+                describe_type(found)
+            ),
+            BlockArity { expected, actual } => write!(
+                f,
+                "this block takes {} input{} but only {} {} given",
+                actual,
+                if actual == 1 { "" } else { "s" },
+                expected,
+                if expected == 1 { "is" } else { "are" }
+            ),
+            FieldMissing {
+                ref name,
+                ref suggestion,
+            } => {
+                write!(f, "field `{}` is missing", name)?;
+                write_suggestion(f, suggestion, "")
+            }
+            FieldOptional(ref name) => write!(f, "field `{}` might be missing", name),
+            UnknownCurrency => write!(
+                f,
+                "the currency of this amount is only known when the script runs, so it must be \
+                 checked with `checkCurrency: ... like: ...` first"
+            ),
+            PayloadMismatch(ref tag) => {
+                write!(f, "case `{}` has a payload on only one side", tag)
+            }
+        }
+    }
+}
+
+fn write_suggestion(f: &mut Formatter, suggestion: &Option<String>, suffix: &str) -> FmtResult {
+    match *suggestion {
+        Some(ref name) => write!(f, ", did you mean `{}{}`?", name, suffix),
+        None => Ok(()),
+    }
+}
+
+/// Describe a path into a type from the innermost location outwards, e.g. "field `cost` of list
+/// element".
+pub fn describe_path(path: &[TypeLoc]) -> String {
+    let parts: Vec<String> = path
+        .iter()
+        .rev()
+        .map(|loc| match *loc {
+            TypeLoc::ListElement => "list element".to_string(),
+            TypeLoc::Field(ref name) => format!("field `{}`", name),
+            TypeLoc::BlockArg(i) => format!("input {} of block", i + 1),
+            TypeLoc::BlockBody => "result of block".to_string(),
+            TypeLoc::Case(ref tag) => format!("case `{}`", tag),
+        })
+        .collect();
+    parts.join(" of ")
+}
+
+/// The values of an enum for messages, like `"pending", "paid"`.
+pub fn quote_all(values: &[String]) -> String {
+    values
+        .iter()
+        .map(|value| format!("{:?}", value))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Describe a type in words, for people who don't read type notation.
+pub fn describe_type(ty: &Type) -> String {
+    use self::Type::*;
+    match *ty {
+        Any => "anything".into(),
+        Never => "nothing".into(),
+        Num => "a number".into(),
+        Str => "a string".into(),
+        Bool => "true or false".into(),
+        Time => "a time".into(),
+        Duration => "a length of time".into(),
+        Money(ref currency) => match **currency {
+            Currency(ref code) => format!("an amount in {}", code),
+            _ => "an amount of money".into(),
+        },
+        Currency(ref code) => code.clone(),
+        List(ref elem) => match **elem {
+            Var(_) | Any => "a list".into(),
+            ref elem => format!("a list where each item is {}", describe_type(elem)),
+        },
+        Optional(ref t) => format!("{}, or nothing", describe_type(t)),
+        Enum(ref values) => format!("one of {}", quote_all(values)),
+        Union(ref cases) => {
+            let mut tags: Vec<&String> = cases.keys().collect();
+            tags.sort();
+            format!(
+                "one of {}",
+                tags.iter()
+                    .map(|tag| format!("`{}`", tag))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        }
+        Opaque(ref name) => format!("a {}", name),
+        Record(_, ref fields) | Update(_, ref fields) => {
+            let mut names: Vec<&String> = fields.keys().collect();
+            names.sort();
+            match names.len() {
+                0 => "an empty record".into(),
+                _ => format!(
+                    "a record with {}",
+                    names
+                        .iter()
+                        .map(|name| format!("`{}`", name))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            }
+        }
+        Block(ref inputs, _) => match inputs.len() {
+            0 => "a block".into(),
+            1 => "a block with 1 input".into(),
+            n => format!("a block with {} inputs", n),
+        },
+        Var(_) => "some value".into(),
+    }
 }
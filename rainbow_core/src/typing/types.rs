@@ -4,18 +4,37 @@ use std::collections::{HashMap, HashSet};
 use std::fmt::{Display, Formatter};
 
 use super::substitution::*;
+use super::type_errors::quote_all;
 
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub enum Type {
+    #[default]
     Any,
     Never,
     Num,
     Str,
     Bool,
     Time,
-    Money,
+    Duration,
+    /// An amount of money, whose currency is a `Currency`, a type variable, or `Any` when it is
+    /// only known at runtime.
+    Money(Box<Type>),
+    /// The currency of an amount of money, by its ISO 4217 code.
+    Currency(String),
     List(Box<Type>),
+    /// A value that may be absent, written `T?`.
+    Optional(Box<Type>),
+    /// A string that can only be one of the given values.
+    Enum(Vec<String>),
+    /// A value tagged with one of several cases, each with the type of its payload if it has one.
+    Union(HashMap<String, Option<Type>>),
+    /// A value the host hands to scripts by name, like `CustomerId`, that they can pass around but
+    /// not look inside. It is only ever the same type as itself.
+    Opaque(String),
     Record(bool, HashMap<String, RecordField>),
+    /// A record written `[ source with a = 1 ]`: the fields given, and every other field of the
+    /// record in the type variable `source`, however many of them turn up later.
+    Update(String, HashMap<String, RecordField>),
     Block(Vec<Type>, Box<Type>),
     Var(String),
 }
@@ -33,6 +52,33 @@ impl Type {
         Type::List(Box::new(t))
     }
 
+    /// A value of type `t`, or nothing at all.
+    pub fn optional(t: Type) -> Type {
+        Type::Optional(Box::new(t))
+    }
+
+    /// A union of the cases in `i`, each with the type of its payload if it has one.
+    pub fn union_from_iter<K: Into<String>, T: IntoIterator<Item = (K, Option<Type>)>>(
+        i: T,
+    ) -> Type {
+        Type::Union(i.into_iter().map(|(k, t)| (k.into(), t)).collect())
+    }
+
+    /// Money in a currency given by its code, like `USD`.
+    pub fn money_in(currency: &str) -> Type {
+        Type::Money(Box::new(Type::Currency(currency.into())))
+    }
+
+    /// A host value that scripts can't look inside, like `Type::opaque("CustomerId")`.
+    pub fn opaque(name: &str) -> Type {
+        Type::Opaque(name.into())
+    }
+
+    /// Money in a currency that's only known at runtime.
+    pub fn money() -> Type {
+        Type::Money(Box::new(Type::Any))
+    }
+
     pub fn record_from_iter<K: Into<String>, T: IntoIterator<Item = (K, Type)>>(i: T) -> Type {
         //use std::iter::FromIterator;
         Type::Record(
@@ -43,7 +89,7 @@ impl Type {
                         name.into(),
                         RecordField {
                             optional: false,
-                            ty: ty,
+                            ty,
                         },
                     )
                 })
@@ -72,24 +118,80 @@ impl Type {
         }
     }
 
+    /// The narrowest type that both `self` and `other` satisfy, if there is one without type
+    /// variables to be solved. A field that only one of two records has becomes optional.
+    pub fn least_upper_bound(&self, other: &Type) -> Option<Type> {
+        use crate::Type::*;
+        match (self, other) {
+            (left, right) if left == right && left.free_vars().is_none() => Some(left.clone()),
+            (List(left), List(right)) => left.least_upper_bound(right).map(Type::list_of),
+            (Optional(left), Optional(right)) => left.least_upper_bound(right).map(Type::optional),
+            (Optional(left), right) | (right, Optional(left)) => {
+                left.least_upper_bound(right).map(Type::optional)
+            }
+            (Enum(left), Enum(right)) => {
+                let mut values = left.clone();
+                values.extend(right.iter().filter(|v| !left.contains(v)).cloned());
+                Some(Enum(values))
+            }
+            (Enum(_), Str) | (Str, Enum(_)) => Some(Str),
+            (Record(false, left), Record(false, right)) => {
+                let mut fields = HashMap::with_capacity(left.len().max(right.len()));
+                for (name, field) in left {
+                    let merged = match right.get(name) {
+                        Some(other) => RecordField::new(
+                            field.ty.least_upper_bound(&other.ty)?,
+                            field.optional || other.optional,
+                        ),
+                        // a field only one of them has is kept, once its type is known
+                        None => RecordField::new(field.ty.least_upper_bound(&field.ty)?, true),
+                    };
+                    fields.insert(name.clone(), merged);
+                }
+                for (name, field) in right.iter().filter(|&(name, _)| !left.contains_key(name)) {
+                    let ty = field.ty.least_upper_bound(&field.ty)?;
+                    fields.insert(name.clone(), RecordField::new(ty, true));
+                }
+                Some(Record(false, fields))
+            }
+            _ => None,
+        }
+    }
+
     /// Perform run-time type-checking of a value
     pub fn satisfied_by_value<V: Value>(&self, value: &V) -> Result<(), Vec<V::Error>> {
+        self.satisfied_by_value_sharing(value, &mut HashMap::new())
+    }
+
+    /// Like `satisfied_by_value`, but amounts whose currency is a type variable must be in the
+    /// currency `currencies` already has for it, so values checked one after another agree.
+    pub fn satisfied_by_value_sharing<V: Value>(
+        &self,
+        value: &V,
+        currencies: &mut HashMap<String, String>,
+    ) -> Result<(), Vec<V::Error>> {
         let mut errors = Vec::with_capacity(10);
-        self.satisfied_by_inner(value, &mut errors, "".into());
-        if errors.len() == 0 {
+        self.satisfied_by_inner(value, &mut errors, currencies, "".into());
+        if errors.is_empty() {
             Ok(())
         } else {
             Err(errors)
         }
     }
 
-    fn satisfied_by_inner<V: Value>(&self, value: &V, errors: &mut Vec<V::Error>, prefix: String) {
+    fn satisfied_by_inner<V: Value>(
+        &self,
+        value: &V,
+        errors: &mut Vec<V::Error>,
+        currencies: &mut HashMap<String, String>,
+        prefix: String,
+    ) {
         use crate::interpreter::Record;
         use crate::Type::*;
         match *self {
             Any => {}
             Never => errors.push(V::Error::from(format!("{}unexpected value", prefix))),
-            Var(_) => {}
+            Var(_) | Update(..) => {}
             Num => {
                 if let Err(err) = value.try_number() {
                     errors.push(V::Error::from(format!("{}{}", prefix, err)));
@@ -110,17 +212,101 @@ impl Type {
                     errors.push(V::Error::from(format!("{}{}", prefix, err)));
                 }
             }
-            Money => {
-                errors.push(V::Error::from("money type is not ready yet".to_string()));
+            Duration => {
+                if let Err(err) = value.try_duration() {
+                    errors.push(V::Error::from(format!("{}{}", prefix, err)));
+                }
+            }
+            Money(ref currency) => match value.try_money() {
+                Ok((actual, _)) => match **currency {
+                    Currency(ref expected) if expected != actual => errors.push(V::Error::from(
+                        format!("{}the amount is in {}, not {}", prefix, actual, expected),
+                    )),
+                    Var(ref name) => match currencies.get(name) {
+                        Some(expected) if expected != actual => {
+                            errors.push(V::Error::from(format!(
+                                "{}the amount is in {}, not {} like the amounts it's used with",
+                                prefix, actual, expected
+                            )))
+                        }
+                        Some(_) => {}
+                        None => {
+                            currencies.insert(name.clone(), actual.to_string());
+                        }
+                    },
+                    _ => {}
+                },
+                Err(err) => {
+                    errors.push(V::Error::from(format!("{}{}", prefix, err)));
+                }
+            },
+            Currency(_) => {}
+            Optional(ref t) => {
+                if !value.is_absent() {
+                    t.satisfied_by_inner(value, errors, currencies, prefix);
+                }
             }
+            Enum(ref values) => match value.try_string() {
+                Ok(s) if values.iter().any(|allowed| allowed == s) => {}
+                Ok(s) => errors.push(V::Error::from(format!(
+                    "{}{:?} is not one of {}",
+                    prefix,
+                    s,
+                    quote_all(values)
+                ))),
+                Err(err) => {
+                    errors.push(V::Error::from(format!("{}{}", prefix, err)));
+                }
+            },
+            Union(ref cases) => match value.try_variant() {
+                Ok((tag, payload)) => match (cases.get(tag), payload) {
+                    (None, _) => errors.push(V::Error::from(format!(
+                        "{}`{}` is not one of the cases",
+                        prefix, tag
+                    ))),
+                    (Some(Some(t)), Some(payload)) => t.satisfied_by_inner(
+                        &payload,
+                        errors,
+                        currencies,
+                        format!("{}case `{}` ", prefix, tag),
+                    ),
+                    (Some(None), None) => {}
+                    (Some(Some(_)), None) => errors.push(V::Error::from(format!(
+                        "{}case `{}` needs a payload",
+                        prefix, tag
+                    ))),
+                    (Some(None), Some(_)) => errors.push(V::Error::from(format!(
+                        "{}case `{}` has no payload",
+                        prefix, tag
+                    ))),
+                },
+                Err(err) => {
+                    errors.push(V::Error::from(format!("{}{}", prefix, err)));
+                }
+            },
+            Opaque(ref expected) => match value.try_opaque() {
+                Ok(actual) if actual == expected => {}
+                Ok(actual) => errors.push(V::Error::from(format!(
+                    "{}is of type {}, not {}",
+                    prefix, actual, expected
+                ))),
+                Err(err) => {
+                    errors.push(V::Error::from(format!("{}{}", prefix, err)));
+                }
+            },
             List(ref t) => match value.try_list() {
                 Ok(list) => {
                     for (i, item) in list.into_iter().enumerate() {
-                        t.satisfied_by_inner(&item, errors, format!("{}item {}: ", prefix, i));
+                        t.satisfied_by_inner(
+                            &item,
+                            errors,
+                            currencies,
+                            format!("{}item {}: ", prefix, i),
+                        );
                     }
                 }
                 Err(err) => {
-                    errors.push(err);
+                    errors.push(V::Error::from(format!("{}{}", prefix, err)));
                 }
             },
             Record(_partial, ref fields) => match value.try_record() {
@@ -131,18 +317,20 @@ impl Type {
                                 field.ty.satisfied_by_inner(
                                     val,
                                     errors,
+                                    currencies,
                                     format!("{}field `{}` ", prefix, name),
                                 );
                             }
-                            (None, false) => {
-                                errors.push(V::Error::from(format!("field `{}` is required", name)))
-                            }
+                            (None, false) => errors.push(V::Error::from(format!(
+                                "{}field `{}` is required",
+                                prefix, name
+                            ))),
                             (None, true) => (),
                         }
                     }
                 }
                 Err(err) => {
-                    errors.push(err);
+                    errors.push(V::Error::from(format!("{}{}", prefix, err)));
                 }
             },
             Block(_, _) => {
@@ -154,20 +342,11 @@ impl Type {
     }
 }
 
-impl ::std::default::Default for Type {
-    fn default() -> Type {
-        Type::Any
-    }
-}
-
 impl Substitutable for Type {
     fn apply_substitution(&self, subs: &Subst) -> Self {
         match *self {
             Type::Block(ref inputs, ref output) => Type::Block(
-                inputs
-                    .into_iter()
-                    .map(|t| t.apply_substitution(subs))
-                    .collect(),
+                inputs.iter().map(|t| t.apply_substitution(subs)).collect(),
                 Box::new(output.apply_substitution(subs)),
             ),
             Type::Record(partial, ref fields) => Type::Record(
@@ -186,7 +365,46 @@ impl Substitutable for Type {
                 Some(t) => t.clone(),
                 None => Type::Var(name.clone()),
             },
+            Type::Update(ref source, ref fields) => {
+                let fields: HashMap<String, RecordField> = fields
+                    .iter()
+                    .map(|(name, field)| {
+                        (
+                            name.clone(),
+                            field.mutate_type(|ty| ty.apply_substitution(subs)),
+                        )
+                    })
+                    .collect();
+                match subs.get(source) {
+                    // once the source can't gain any more fields, the update is a record too
+                    Some(Type::Record(false, source_fields)) => {
+                        let mut merged = source_fields.clone();
+                        merged.extend(fields);
+                        Type::Record(false, merged)
+                    }
+                    Some(Type::Update(source, source_fields)) => {
+                        let mut merged = source_fields.clone();
+                        merged.extend(fields);
+                        Type::Update(source.clone(), merged)
+                    }
+                    Some(Type::Var(source)) => Type::Update(source.clone(), fields),
+                    _ => Type::Update(source.clone(), fields),
+                }
+            }
             Type::List(ref element) => Type::List(Box::new(element.apply_substitution(subs))),
+            Type::Optional(ref t) => Type::Optional(Box::new(t.apply_substitution(subs))),
+            Type::Union(ref cases) => Type::Union(
+                cases
+                    .iter()
+                    .map(|(tag, payload)| {
+                        (
+                            tag.clone(),
+                            payload.as_ref().map(|ty| ty.apply_substitution(subs)),
+                        )
+                    })
+                    .collect(),
+            ),
+            Type::Money(ref currency) => Type::Money(Box::new(currency.apply_substitution(subs))),
             _ => self.clone(),
         }
     }
@@ -202,11 +420,24 @@ impl Substitutable for Type {
                 Some(vars)
             }
             Type::List(ref element) => element.free_vars(),
+            Type::Optional(ref t) => t.free_vars(),
+            Type::Union(ref cases) => cases
+                .values()
+                .flatten()
+                .fold(None as Option<HashSet<String>>, extend_vars),
+            Type::Money(ref currency) => currency.free_vars(),
             Type::Record(_partial, ref fields) => fields
                 .iter()
                 .fold(None as Option<HashSet<String>>, |vars, (_, field)| {
                     extend_vars(vars, field.get_type())
                 }),
+            Type::Update(ref source, ref fields) => {
+                let mut vars = Some(Some(source.clone()).into_iter().collect());
+                for field in fields.values() {
+                    vars = extend_vars(vars, field.get_type());
+                }
+                vars
+            }
             _ => None,
         }
     }
@@ -224,42 +455,61 @@ impl Display for Type {
             Str => f.write_str("string"),
             Bool => f.write_str("boolean"),
             Time => f.write_str("time"),
-            Money => f.write_str("money"),
+            Duration => f.write_str("duration"),
+            Money(ref currency) => match **currency {
+                Any => f.write_str("money"),
+                ref currency => write!(f, "money<{}>", currency),
+            },
+            Currency(ref code) => f.write_str(code),
             List(ref t) => write!(f, "[ {}... ]", t),
+            Optional(ref t) => write!(f, "{}?", t),
+            Enum(ref values) => {
+                f.write_char('<')?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" |")?;
+                    }
+                    write!(f, " {:?}", value)?;
+                }
+                f.write_str(" >")
+            }
+            Union(ref cases) => {
+                let mut tags: Vec<&String> = cases.keys().collect();
+                tags.sort();
+                f.write_char('<')?;
+                for (i, tag) in tags.into_iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" |")?;
+                    }
+                    write!(f, " {}", tag)?;
+                    if let Some(ref payload) = cases[tag] {
+                        write!(f, "({})", payload)?;
+                    }
+                }
+                f.write_str(" >")
+            }
+            Opaque(ref name) => write!(f, "opaque<{}>", name),
             Var(ref name) => f.write_str(name),
             Record(partial, ref fields) => {
                 f.write_char('[')?;
                 if partial {
                     f.write_char('?')?;
                 }
-
-                let mut field_vec: Vec<(&String, &RecordField)> = fields.iter().collect();
-                field_vec.sort_by(|&(name1, f1), &(name2, f2)| -> Ordering {
-                    if f1.optional == f2.optional {
-                        name1.cmp(name2)
-                    } else if f1.optional {
-                        Ordering::Greater
-                    } else {
-                        Ordering::Less
-                    }
-                });
-
-                for &(name, field) in field_vec.iter() {
-                    write!(f, " {}", name)?;
-                    if field.optional {
-                        f.write_char('?')?;
-                    };
-                    write!(f, "={}", field.ty)?;
-                }
+                write_fields(f, fields)?;
                 f.write_char(' ')?;
                 if partial {
                     f.write_char('?')?;
                 }
                 f.write_char(']')
             }
+            Update(ref source, ref fields) => {
+                write!(f, "[ {} with", source)?;
+                write_fields(f, fields)?;
+                f.write_str(" ]")
+            }
             Block(ref inputs, ref output) => {
                 f.write_str("{ ")?;
-                if inputs.len() > 0 {
+                if !inputs.is_empty() {
                     for input in inputs.iter() {
                         write!(f, "{} ", input)?;
                     }
@@ -271,6 +521,31 @@ impl Display for Type {
     }
 }
 
+/// Write the fields of a record, required ones first and each in order of their names.
+fn write_fields(f: &mut Formatter, fields: &HashMap<String, RecordField>) -> std::fmt::Result {
+    use std::fmt::Write;
+
+    let mut field_vec: Vec<(&String, &RecordField)> = fields.iter().collect();
+    field_vec.sort_by(|(name1, f1), (name2, f2)| -> Ordering {
+        if f1.optional == f2.optional {
+            name1.cmp(name2)
+        } else if f1.optional {
+            Ordering::Greater
+        } else {
+            Ordering::Less
+        }
+    });
+
+    for (name, field) in field_vec {
+        write!(f, " {}", name)?;
+        if field.optional {
+            f.write_char('?')?;
+        };
+        write!(f, "={}", field.ty)?;
+    }
+    Ok(())
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct RecordField {
     optional: bool,
@@ -279,10 +554,7 @@ pub struct RecordField {
 
 impl RecordField {
     pub fn new(ty: Type, optional: bool) -> RecordField {
-        RecordField {
-            ty: ty,
-            optional: optional,
-        }
+        RecordField { ty, optional }
     }
 
     pub fn required(&self) -> bool {
@@ -340,15 +612,19 @@ mod tests {
 
     #[test]
     fn test_satisfied_by_primitive_types() {
-        let pairs = vec![
+        let pairs = [
             (Type::Num, Value::from(3f64)),
             (Type::Str, Value::from("hello")),
             (Type::Bool, Value::from(false)),
             (Type::Time, Value::from(3u64)),
+            (
+                Type::money(),
+                Value::from((String::from("USD"), "1.50".parse().unwrap())),
+            ),
         ];
-        for (i, &(ref ty, ref val)) in (&pairs).into_iter().enumerate() {
+        for (i, (ty, val)) in pairs.iter().enumerate() {
             assert_eq!(ty.satisfied_by_value(val), Ok(()));
-            for (j, &(_, ref wrong_val)) in (&pairs).into_iter().enumerate() {
+            for (j, (_, wrong_val)) in pairs.iter().enumerate() {
                 if i != j {
                     assert!(ty.satisfied_by_value(wrong_val).is_err());
                 }
@@ -356,6 +632,16 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_satisfied_by_money_checks_currency() {
+        let usd = Value::from((String::from("USD"), "1.50".parse().unwrap()));
+        assert_eq!(Type::money_in("USD").satisfied_by_value(&usd), Ok(()));
+        assert_eq!(
+            Type::money_in("EUR").satisfied_by_value(&usd),
+            Err(vec!["the amount is in USD, not EUR".to_string()])
+        );
+    }
+
     #[test]
     fn test_satisfied_by_lists() {
         use std::iter::FromIterator;
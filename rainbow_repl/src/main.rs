@@ -1,20 +1,29 @@
 use std::cell::RefCell;
 use std::rc::Rc;
 use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read};
+use std::process;
 use rustyline::error::ReadlineError;
 use rustyline::Editor;
 use rustyline::completion::Completer;
 
-use rainbow_core::{INamespace, Namespace, Scope, Script, SharedNamespace, Type, TypeCheckerResult};
+use rainbow_core::diagnostics::{render_type_errors, Style};
+use rainbow_core::frontend::{format_source, DEFAULT_WIDTH};
+use rainbow_core::{CompileError, CompileOptions, INamespace, Namespace, Script, SharedNamespace, Type, TypeCheckerResult};
 use rainbow_core::standalone::Value;
 
-use rainbow_core;
-
 pub struct REPL {
     ns: SharedNamespace<Value>,
     env: RefCell<HashMap<String, (Value, Type)>>,
 }
 
+impl Default for REPL {
+    fn default() -> REPL {
+        REPL::new()
+    }
+}
+
 impl REPL {
     pub fn new() -> REPL {
         REPL {
@@ -23,19 +32,32 @@ impl REPL {
         }
     }
 
+    /// Types of the variables defined with `:set`, declared as globals for every compile.
+    fn declared_globals(&self) -> HashMap<String, Type> {
+        self.env
+            .borrow()
+            .iter()
+            .map(|(name, (_, ty))| (name.clone(), ty.clone()))
+            .collect()
+    }
+
     pub fn type_of(&self, input: &str) -> Result<TypeCheckerResult, String> {
-        Script::compile(self.ns.clone(), input)
+        let options = CompileOptions::lenient().with_globals(self.declared_globals());
+        Script::compile_with(self.ns.clone(), input, options)
             .map_err(|err| format!("{}", err))
             .map(|script| script.typer_result)
     }
 
     pub fn eval(&self, input: &str) -> Result<(Value, Type), String> {
-        Script::compile(self.ns.clone(), input)
-            .map_err(|err| format!("{}", err))
+        Script::compile_with_globals(self.ns.clone(), input, self.declared_globals())
+            .map_err(|err| match err {
+                CompileError::TypeErrors(ref errors) => render_type_errors(input, errors, Style::Ansi),
+                err => format!("{}", err),
+            })
             .and_then(|script| {
                 let env = &*self.env.borrow();
                 script
-                    .eval(env.clone().into_iter().map(|(k, (v, t))| (k, v)).collect())
+                    .eval(env.clone().into_iter().map(|(k, (v, _))| (k, v)).collect())
                     .map(|v| (v, script.typer_result.output))
             })
     }
@@ -45,7 +67,70 @@ impl REPL {
     }
 }
 
+/// `rainbow fmt [--width N] [FILE...]` formats each file in place, or stdin to stdout when no files
+/// are given. Returns the exit code.
+fn fmt(args: &[String]) -> i32 {
+    let mut width = DEFAULT_WIDTH;
+    let mut paths = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--width" {
+            match args.next().and_then(|w| w.parse().ok()) {
+                Some(w) => width = w,
+                None => {
+                    eprintln!("--width needs a number of columns, e.g. `--width 100`");
+                    return 2;
+                }
+            }
+        } else {
+            paths.push(arg.clone());
+        }
+    }
+
+    if paths.is_empty() {
+        let mut src = String::new();
+        if let Err(err) = io::stdin().read_to_string(&mut src) {
+            eprintln!("cannot read stdin: {}", err);
+            return 1;
+        }
+        return match format_source(&src, width) {
+            Ok(formatted) => {
+                print!("{}", formatted);
+                0
+            }
+            Err(err) => {
+                eprintln!("{}", err);
+                1
+            }
+        };
+    }
+
+    let mut status = 0;
+    for path in paths {
+        let result = fs::read_to_string(&path)
+            .map_err(|err| format!("cannot read {}: {}", path, err))
+            .and_then(|src| {
+                let formatted = format_source(&src, width)
+                    .map_err(|err| format!("{}:\n{}", path, err))?;
+                if formatted == src {
+                    return Ok(());
+                }
+                fs::write(&path, formatted).map_err(|err| format!("cannot write {}: {}", path, err))
+            });
+        if let Err(message) = result {
+            eprintln!("{}", message);
+            status = 1;
+        }
+    }
+    status
+}
+
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(|arg| arg.as_str()) == Some("fmt") {
+        process::exit(fmt(&args[1..]));
+    }
+
     let mut reader: Editor<Rc<REPL>> = Editor::new();
 
     let hist_path = std::env::home_dir().map(|path| path.join(".rainbow_history"));
@@ -68,7 +153,7 @@ fn main() {
     reader.set_completer(Some(repl.clone()));
 
     println!("This is Rainbow (press Ctrl-D to exit)");
-    println!("");
+    println!();
 
     loop {
         let mut line = match reader.readline("🌈 ") {
@@ -90,7 +175,7 @@ fn main() {
         }
 
         let (cmd, rest) = match line.trim().find(|ch: char| ch.is_whitespace()) {
-            Some(pos) => (&line[..pos], line[pos..].trim_left()),
+            Some(pos) => (&line[..pos], line[pos..].trim_start()),
             None => (line.as_str(), ""),
         };
 
@@ -114,12 +199,18 @@ fn main() {
                         }
                         println!("{} ~ {}", name, ty);
                     }
+                    if !result.errors.is_empty() {
+                        println!("\n{}", render_type_errors(rest, &result.errors, Style::Ansi));
+                    }
                 }
                 Err(message) => println!("{}", message),
             },
             ":func" => match repl.ns.borrow().get_signature(rest) {
                 None => println!("`{}` is not defined", rest),
-                Some(sig) => println!("{}", sig),
+                Some(sig) => {
+                    let ns = repl.ns.borrow();
+                    println!("{}", sig.map_names(|id| ns.lookup_symbol(*id).clone()));
+                }
             },
             ":vars" => for (name, (val, ty)) in repl.as_ref().env.borrow().iter() {
                 println!("{} = {} :: {}", name, val, ty);
@@ -129,7 +220,7 @@ fn main() {
                     println!(":set needs a variable name and expression. E.g. `:set foo \"hello\"");
                 }
                 Some(pos) => {
-                    repl.eval(rest[pos..].trim_left())
+                    repl.eval(rest[pos..].trim_start())
                         .map(|(val, ty)| {
                             let name = &rest[..pos];
                             println!("{} = {} ~ {}", name, val, ty);
@@ -149,8 +240,8 @@ impl Completer for REPL {
         use std::iter::FromIterator;
         use rustyline::completion::extract_word;
 
-        let break_chars = BTreeSet::from_iter(vec![' ', '\t', '[', '{'].into_iter());
-        let (start, word) = extract_word(&line, pos, &break_chars);
+        let break_chars = BTreeSet::from_iter(vec![' ', '\t', '[', '{']);
+        let (start, word) = extract_word(line, pos, &break_chars);
 
         if start == 0 && word.starts_with(":") {
             let matches = vec![":eval", ":type", ":vars", ":func"]